@@ -0,0 +1,43 @@
+//! Times the lexer on a large generated corpus:
+//!
+//!     cargo run --release --example lexer_throughput
+//!
+//! The lexer is compiled straight from its source rather than through the library, so this
+//! file also runs unchanged in older checkouts, including ones from before the library
+//! existed. To compare two revisions, copy it into a checkout of each (e.g. made with
+//! `git worktree add ../before <rev>`) and run it in both.
+
+#[path = "../src/lexer/mod.rs"]
+#[allow(dead_code)]
+mod lexer;
+
+use lexer::Lexer;
+
+fn main() {
+    let unit = "let five = 5;
+let tenish = 2*10.0/2;
+let add = fn(x, y) { x + y; };
+// a line comment
+if ((!true == false) != true) { return naïve; } /* a block comment */
+";
+    let input = unit.repeat(200_000);
+
+    // the fastest of a few runs, to keep noise from other processes out of the figure
+    let mut count = 0;
+    let best = (0..5)
+        .map(|_| {
+            let start = std::time::Instant::now();
+            count = Lexer::new(&input).count();
+            start.elapsed()
+        })
+        .min()
+        .expect("ran at least once");
+
+    println!(
+        "lexed {} tokens from {} bytes in {:?} ({:.0} MB/s)",
+        count,
+        input.len(),
+        best,
+        input.len() as f64 / best.as_secs_f64() / 1e6
+    );
+}
@@ -0,0 +1,254 @@
+//! Command line parsing for the `monkey` binary.
+
+pub const USAGE: &str = "\
+usage: monkey [options] [command]
+
+commands:
+  run [file] [args]   parse a script and report its diagnostics. There's no evaluator
+                      yet, so arguments after the file are accepted but ignored.
+                      Without a file, uses the project's entry file, or stdin when
+                      there is no project; `-` also means stdin
+    --watch           check the script again whenever it changes
+  repl                start the interactive REPL (the default on a terminal)
+  doc <file>          print a script's documentation comments as Markdown
+  fmt [files]         format scripts in place (stdin to stdout when no file is given)
+    --check           only list the files that aren't formatted, failing if there are any
+
+options:
+  -e, --eval <src>    parse the program given as an argument and report its diagnostics
+  --color <when>      colour output: always, never or auto (overrides $MONKEY_COLOR)
+  --dump-ast=<fmt>    print the AST of a file (or stdin) as sexpr or dot
+  --json-repl         speak the line-delimited JSON REPL protocol
+  -h, --help          print this message
+
+exit status:
+  0  success
+  1  the program failed to parse, or fmt could not format it or found it unformatted
+  2  invalid command line arguments
+  3  a script could not be read
+  4  the project's monkey.toml is invalid
+";
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_PARSE_ERROR: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_IO_ERROR: i32 = 3;
+pub const EXIT_CONFIG_ERROR: i32 = 4;
+
+/// Commands that are planned but have nothing behind them yet
+const UNAVAILABLE: &[&str] = &["lint", "compile", "test", "lsp"];
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Parses the script at the path (`-` for stdin), or the project's entry file, and
+    /// reports its diagnostics
+    Run {
+        path: Option<String>,
+        watch: bool,
+        /// everything after the script path, for the script itself
+        args: Vec<String>,
+    },
+    Eval(String),
+    Repl,
+    /// Renders the doc comments of the script at the path
+    Doc(String),
+    /// Formats the scripts at the paths, or stdin
+    Fmt {
+        check: bool,
+        paths: Vec<String>,
+    },
+    JsonRepl,
+    DumpAst {
+        format: String,
+        path: Option<String>,
+    },
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    /// `None` leaves the choice to `MONKEY_COLOR` and whether the output is a terminal
+    pub color: Option<bool>,
+    /// `None` when no command was given, which runs piped input or starts the REPL
+    pub command: Option<Command>,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+    let mut args = args.into_iter();
+    let mut cli = Cli {
+        color: None,
+        command: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let command = match arg.as_str() {
+            "-h" | "--help" => Command::Help,
+            "--json-repl" => Command::JsonRepl,
+            "-e" | "--eval" => Command::Eval(
+                args.next()
+                    .ok_or_else(|| format!("{} expects a program to run", arg))?,
+            ),
+            "--color" => {
+                let when = args.next().ok_or("--color expects always, never or auto")?;
+                cli.color = parse_color(&when)?;
+                continue;
+            }
+            "run" => {
+                let mut path = args.next();
+                let watch = path.as_deref() == Some("--watch");
+                if watch {
+                    path = args.next();
+                }
+                Command::Run {
+                    path,
+                    watch,
+                    args: args.by_ref().collect(),
+                }
+            }
+            "repl" => Command::Repl,
+            "doc" => Command::Doc(args.next().ok_or("doc expects a script")?),
+            "fmt" => {
+                let mut paths: Vec<String> = args.by_ref().collect();
+                let check = paths.iter().any(|p| p == "--check");
+                paths.retain(|p| p != "--check");
+                Command::Fmt { check, paths }
+            }
+            _ => {
+                if let Some(when) = arg.strip_prefix("--color=") {
+                    cli.color = parse_color(when)?;
+                    continue;
+                } else if let Some(format) = arg.strip_prefix("--dump-ast=") {
+                    Command::DumpAst {
+                        format: format.into(),
+                        path: args.next(),
+                    }
+                } else if UNAVAILABLE.contains(&arg.as_str()) {
+                    return Err(format!("the '{}' command is not available yet", arg));
+                } else {
+                    return Err(format!("unrecognised argument '{}'", arg));
+                }
+            }
+        };
+
+        if let Some(extra) = args.next() {
+            return Err(format!("unexpected argument '{}'", extra));
+        }
+        cli.command = Some(command);
+    }
+
+    Ok(cli)
+}
+
+fn parse_color(when: &str) -> Result<Option<bool>, String> {
+    match when {
+        "always" => Ok(Some(true)),
+        "never" => Ok(Some(false)),
+        "auto" => Ok(None),
+        _ => Err(format!(
+            "--color expects always, never or auto, not '{}'",
+            when
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_args, Cli, Command};
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse(&[]),
+            Ok(Cli {
+                color: None,
+                command: None
+            })
+        );
+        assert_eq!(
+            parse(&["--color", "never", "run", "main.mk"]),
+            Ok(Cli {
+                color: Some(false),
+                command: Some(Command::Run {
+                    path: Some("main.mk".into()),
+                    watch: false,
+                    args: vec![]
+                })
+            })
+        );
+        assert_eq!(
+            parse(&["run", "--watch", "main.mk"]).map(|cli| cli.command),
+            Ok(Some(Command::Run {
+                path: Some("main.mk".into()),
+                watch: true,
+                args: vec![]
+            }))
+        );
+        assert_eq!(
+            parse(&["run", "main.mk", "-e", "run"]).map(|cli| cli.command),
+            Ok(Some(Command::Run {
+                path: Some("main.mk".into()),
+                watch: false,
+                args: vec!["-e".into(), "run".into()]
+            }))
+        );
+        assert_eq!(
+            parse(&["fmt", "a.mk", "--check", "b.mk"]).map(|cli| cli.command),
+            Ok(Some(Command::Fmt {
+                check: true,
+                paths: vec!["a.mk".into(), "b.mk".into()]
+            }))
+        );
+        assert_eq!(
+            parse(&["--color=always", "repl"]),
+            Ok(Cli {
+                color: Some(true),
+                command: Some(Command::Repl)
+            })
+        );
+        assert_eq!(
+            parse(&["--dump-ast=dot"]).map(|cli| cli.command),
+            Ok(Some(Command::DumpAst {
+                format: "dot".into(),
+                path: None
+            }))
+        );
+        assert_eq!(
+            parse(&["-e", "let x = 1;"]).map(|cli| cli.command),
+            Ok(Some(Command::Eval("let x = 1;".into())))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert_eq!(
+            parse(&["lsp"]),
+            Err("the 'lsp' command is not available yet".into())
+        );
+        assert_eq!(
+            parse(&["--colour"]),
+            Err("unrecognised argument '--colour'".into())
+        );
+        assert_eq!(
+            parse(&["repl", "extra"]),
+            Err("unexpected argument 'extra'".into())
+        );
+        assert_eq!(
+            parse(&["--color", "sometimes"]),
+            Err("--color expects always, never or auto, not 'sometimes'".into())
+        );
+        assert_eq!(parse(&["-e"]), Err("-e expects a program to run".into()));
+        assert_eq!(parse(&["doc"]), Err("doc expects a script".into()));
+        assert_eq!(
+            parse(&["run", "--watch"]).map(|cli| cli.command),
+            Ok(Some(Command::Run {
+                path: None,
+                watch: true,
+                args: vec![]
+            }))
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Project settings from a `monkey.toml` file.
+//!
+//! Only the subset of TOML the settings need is understood: `[table]` headers, and
+//! `key = value` pairs whose values are booleans, integers, basic strings or arrays of
+//! those. Comments start with `#`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+pub const FILE_NAME: &str = "monkey.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    Array(Vec<Value>),
+}
+
+/// Settings keyed by their dotted path, e.g. `fmt.spaces_around_operators`
+pub type Table = HashMap<String, Value>;
+
+/// Parses a settings file, reporting the first malformed line as `line N: problem`
+pub fn parse(src: &str) -> Result<Table, String> {
+    let mut table = Table::new();
+    let mut section = String::new();
+
+    for (i, line) in src.lines().enumerate() {
+        let error = |problem: &str| format!("line {}: {}", i + 1, problem);
+
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header
+                .strip_suffix(']')
+                .ok_or_else(|| error("unclosed table header"))?;
+            section = format!("{}.", name.trim());
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected `key = value`"))?;
+        let key = format!("{}{}", section, key.trim());
+        let value = parse_value(value.trim()).ok_or_else(|| error("invalid value"))?;
+        if table.insert(key.clone(), value).is_some() {
+            return Err(error(&format!("duplicate key `{}`", key)));
+        }
+    }
+
+    Ok(table)
+}
+
+/// The nearest `monkey.toml` in `dir` or one of its ancestors, which marks the project root
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Reads and parses the settings file for the project containing `dir`, if there is one
+pub fn load(dir: &Path) -> Result<Option<(PathBuf, Table)>, String> {
+    let Some(path) = find(dir) else {
+        return Ok(None);
+    };
+
+    let src = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let table = parse(&src).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Some((path, table)))
+}
+
+/// The `[project]` table, which describes a multi-file project
+#[derive(Debug, PartialEq)]
+pub struct Project {
+    /// the directory holding `monkey.toml`; relative paths in it are relative to here
+    pub root: PathBuf,
+    /// the script `monkey run` runs when not given one
+    pub entry: Option<PathBuf>,
+}
+
+impl Project {
+    /// Reads the project settings from the table parsed from the file at `manifest`
+    pub fn from_table(manifest: &Path, table: &Table) -> Result<Self, String> {
+        let root = manifest.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut project = Project { root, entry: None };
+
+        for (key, value) in table {
+            let Some(name) = key.strip_prefix("project.") else {
+                continue;
+            };
+            match (name, value) {
+                ("entry", Value::String(entry)) => project.entry = Some(project.root.join(entry)),
+                ("entry", _) => return Err(format!("`{}` must be a string", key)),
+                _ => return Err(format!("unknown project setting `{}`", key)),
+            }
+        }
+
+        Ok(project)
+    }
+}
+
+/// Removes a trailing `# comment`, ignoring `#`s inside strings
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(src: &str) -> Option<Value> {
+    match src {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        _ => {}
+    }
+
+    if let Some(inner) = src.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        return (!inner.contains('"')).then(|| Value::String(inner.into()));
+    }
+
+    if let Some(inner) = src.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        return inner
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(parse_value)
+            .collect::<Option<_>>()
+            .map(Value::Array);
+    }
+
+    src.parse().ok().map(Value::Integer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Project, Value};
+
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_parse() {
+        let src = r#"
+# project settings
+name = "demo # not a comment"
+
+[fmt]
+spaces_around_operators = false   # compact
+width = 80
+paths = ["lib", "vendor",]
+"#;
+
+        let table = parse(src).expect("should parse");
+        assert_eq!(table["name"], Value::String("demo # not a comment".into()));
+        assert_eq!(table["fmt.spaces_around_operators"], Value::Bool(false));
+        assert_eq!(table["fmt.width"], Value::Integer(80));
+        assert_eq!(
+            table["fmt.paths"],
+            Value::Array(vec![
+                Value::String("lib".into()),
+                Value::String("vendor".into())
+            ])
+        );
+    }
+
+    #[test]
+    fn test_project() {
+        let table = parse("[project]\nentry = \"src/main.mk\"").unwrap();
+        assert_eq!(
+            Project::from_table(Path::new("demo/monkey.toml"), &table),
+            Ok(Project {
+                root: PathBuf::from("demo"),
+                entry: Some(PathBuf::from("demo/src/main.mk"))
+            })
+        );
+
+        let table = parse("[project]\nentry = 1").unwrap();
+        assert_eq!(
+            Project::from_table(Path::new("monkey.toml"), &table),
+            Err("`project.entry` must be a string".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("[fmt"), Err("line 1: unclosed table header".into()));
+        assert_eq!(
+            parse("\nwidth"),
+            Err("line 2: expected `key = value`".into())
+        );
+        assert_eq!(parse("width = wide"), Err("line 1: invalid value".into()));
+        assert_eq!(
+            parse("a = 1\na = 2"),
+            Err("line 2: duplicate key `a`".into())
+        );
+    }
+}
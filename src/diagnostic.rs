@@ -0,0 +1,238 @@
+use crate::{
+    lexer::tokens::Span,
+    parser::{ast::Program, error::ParseError},
+};
+
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A problem report in a uniform shape, whichever stage produced it, for tools to filter
+/// and render
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// a stable code such as `E0001`
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Option<Span>,
+    pub file_name: Option<String>,
+    pub help: Option<String>,
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(e: &ParseError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: Some(e.code.as_str()),
+            message: e.message.clone(),
+            span: e.span,
+            file_name: e.file_name.clone(),
+            help: e.help.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file_name, self.span) {
+            (Some(file), Some(span)) => {
+                write!(f, "{}:{}:{}: ", file, span.start.line, span.start.col + 1)?
+            }
+            (Some(file), None) => write!(f, "{}: ", file)?,
+            (None, Some(span)) => write!(f, "{}:{}: ", span.start.line, span.start.col + 1)?,
+            (None, None) => {}
+        }
+
+        write!(f, "{}", self.severity)?;
+        if let Some(code) = self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)?;
+
+        if let Some(help) = &self.help {
+            write!(f, "\n  = help: {}", help)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// All the diagnostics for one source, rendered in source order and followed by a count
+/// such as `2 errors, 1 warning`
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new(mut items: Vec<Diagnostic>) -> Self {
+        // diagnostics without a location (e.g. "too many errors") go last
+        items.sort_by_key(|d| {
+            d.span
+                .map_or((usize::MAX, usize::MAX), |s| (s.start.line, s.start.col))
+        });
+        Diagnostics { items }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Renders every diagnostic and the summary, one per line, colouring errors and
+    /// warnings with ANSI escapes if `color` is set
+    pub fn render(&self, color: bool) -> String {
+        let mut out = String::new();
+        if self.is_empty() {
+            return out;
+        }
+
+        for d in &self.items {
+            let escape = match d.severity {
+                Severity::Error => "\x1b[31m",
+                Severity::Warning => "\x1b[33m",
+                Severity::Note => "",
+            };
+            if color && !escape.is_empty() {
+                out.push_str(&format!("{}{}\x1b[0m\n", escape, d));
+            } else {
+                out.push_str(&format!("{}\n", d));
+            }
+        }
+
+        out.push_str(&self.summary());
+        out.push('\n');
+        out
+    }
+
+    fn summary(&self) -> String {
+        let count = |severity| self.items.iter().filter(|d| d.severity == severity).count();
+        let plural =
+            |n: usize, noun: &str| format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" });
+
+        let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+        match (errors, warnings) {
+            (_, 0) => plural(errors, "error"),
+            (0, _) => plural(warnings, "warning"),
+            _ => format!(
+                "{}, {}",
+                plural(errors, "error"),
+                plural(warnings, "warning")
+            ),
+        }
+    }
+}
+
+impl From<&[ParseError]> for Diagnostics {
+    fn from(errors: &[ParseError]) -> Self {
+        Diagnostics::new(errors.iter().map(Diagnostic::from).collect())
+    }
+}
+
+impl Program {
+    /// The program's errors and warnings together
+    pub fn diagnostics(&self) -> Diagnostics {
+        let errors = self.errors.iter().map(Diagnostic::from);
+        Diagnostics::new(errors.chain(self.warnings.iter().cloned()).collect())
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+/// The colour choice a `MONKEY_COLOR` value (`always` or `never`) makes. `None` leaves it
+/// to whether the diagnostics are going to a terminal.
+pub fn color_choice(monkey_color: Option<&str>) -> Option<bool> {
+    match monkey_color {
+        Some("always") => Some(true),
+        Some("never") => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Diagnostics, Severity};
+    use crate::{
+        lexer::{tokens::TokenLoc, Lexer},
+        parser::Parser,
+    };
+
+    #[test]
+    fn test_from_parse_errors() {
+        let program = Parser::new(Lexer::new("5; let x 1;"))
+            .with_file_name("main.mk")
+            .parse();
+        let diagnostics: Vec<Diagnostic> = program.errors.iter().map(Diagnostic::from).collect();
+
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].code, Some("E0001"));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "main.mk:1:1: error[E0001]: unexpected token: Int
+  = help: statements start with 'let'"
+        );
+
+        let span = diagnostics[1].span.unwrap();
+        assert_eq!(
+            span.start,
+            TokenLoc {
+                line: 1,
+                col: 9,
+                offset: 9
+            }
+        );
+        assert_eq!(
+            span.end,
+            TokenLoc {
+                line: 1,
+                col: 10,
+                offset: 10
+            }
+        );
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "main.mk:1:10: error[E0002]: expected '=' after identifier 'x', found '1'"
+        );
+    }
+
+    #[test]
+    fn test_render_diagnostics() {
+        let program = Parser::new(Lexer::new("let x 1;\n5;"))
+            .with_file_name("main.mk")
+            .parse();
+
+        let mut diagnostics = Diagnostics::from(&program.errors[..]);
+        diagnostics.items.reverse();
+        let diagnostics = Diagnostics::new(diagnostics.items);
+
+        assert_eq!(
+            diagnostics.to_string(),
+            "main.mk:1:7: error[E0002]: expected '=' after identifier 'x', found '1'
+main.mk:2:1: error[E0001]: unexpected token: Int
+  = help: statements start with 'let'
+2 errors
+"
+        );
+        assert_eq!(Diagnostics::default().to_string(), "");
+    }
+}
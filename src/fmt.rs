@@ -0,0 +1,331 @@
+//! Prints programs back out in a canonical layout: one statement per line, single spaces
+//! around binary operators and only the parentheses the precedence rules need.
+
+use crate::{
+    config::{Table, Value},
+    lexer::{
+        self,
+        tokens::{TokenLoc, TokenType},
+        Lexer,
+    },
+    parser::{
+        ast::{Expression, Program, Statement},
+        error::ParseError,
+        Parser, Precedence,
+    },
+};
+
+/// Layout choices, read from the `[fmt]` table of `monkey.toml`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatConfig {
+    /// `a + b` rather than `a+b`
+    pub spaces_around_operators: bool,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig {
+            spaces_around_operators: true,
+        }
+    }
+}
+
+impl FormatConfig {
+    pub fn from_table(table: &Table) -> Result<Self, String> {
+        let mut config = FormatConfig::default();
+
+        for (key, value) in table {
+            let Some(name) = key.strip_prefix("fmt.") else {
+                continue;
+            };
+            match (name, value) {
+                ("spaces_around_operators", Value::Bool(b)) => config.spaces_around_operators = *b,
+                ("spaces_around_operators", _) => {
+                    return Err(format!("`{}` must be true or false", key))
+                }
+                _ => return Err(format!("unknown formatter setting `{}`", key)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// the source has syntax errors, so there's no AST to print
+    Parse(Vec<ParseError>),
+    /// the source has ordinary comments, or doc comments that don't document a `let`,
+    /// which aren't in the AST and would be lost
+    Comments,
+}
+
+/// Formats `src`, keeping a leading `#!` line and the blank lines between statements
+pub fn format_source(src: &str, config: &FormatConfig) -> Result<String, FormatError> {
+    let mut lexer = Lexer::new(src);
+    let doc_lines = lexer
+        .by_ref()
+        .filter(|t| t.typ == TokenType::DocComment)
+        .count();
+    if lexer.skipped_comment() {
+        return Err(FormatError::Comments);
+    }
+
+    let program = Parser::new(Lexer::new(src)).parse();
+    if !program.errors.is_empty() {
+        return Err(FormatError::Parse(program.errors));
+    }
+
+    // doc comments anywhere but directly before a `let` are dropped by the parser
+    let documented_lines: usize = program
+        .statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::LetStatement { doc, .. } => {
+                doc.as_ref().map_or(0, |d| d.split('\n').count())
+            }
+        })
+        .sum();
+    if documented_lines != doc_lines {
+        return Err(FormatError::Comments);
+    }
+
+    let mut out = String::new();
+    if let Some(shebang) = src.lines().next().filter(|l| l.starts_with("#!")) {
+        out.push_str(shebang);
+        out.push('\n');
+    }
+    out.push_str(&format_program(&program, config));
+    Ok(out)
+}
+
+pub fn format_program(program: &Program, config: &FormatConfig) -> String {
+    let mut out = String::new();
+
+    let mut previous_end = None;
+    for statement in &program.statements {
+        let (start, end) = line_range(statement);
+        if previous_end.is_some_and(|line| start > line + 1) {
+            out.push('\n');
+        }
+        previous_end = Some(end);
+
+        out.push_str(&format_statement(statement, config));
+        out.push('\n');
+    }
+
+    out
+}
+
+pub fn format_statement(statement: &Statement, config: &FormatConfig) -> String {
+    match statement {
+        Statement::LetStatement {
+            identifier,
+            type_annotation,
+            value,
+            doc,
+            ..
+        } => {
+            let mut out = String::new();
+            for line in doc.iter().flat_map(|d| d.split('\n')) {
+                if line.is_empty() {
+                    out.push_str("///\n");
+                } else {
+                    out.push_str(&format!("/// {}\n", line));
+                }
+            }
+
+            out.push_str("let ");
+            out.push_str(&identifier.value);
+            if let Some(type_name) = type_annotation {
+                out.push_str(": ");
+                out.push_str(&type_name.value);
+            }
+            out.push_str(" = ");
+            out.push_str(&format_expression(value, config));
+            out.push(';');
+            out
+        }
+    }
+}
+
+pub fn format_expression(expression: &Expression, config: &FormatConfig) -> String {
+    match expression {
+        Expression::Dummy => "dummy".into(),
+        Expression::Identifier(identifier) => identifier.value.clone(),
+        Expression::IntegerLiteral { token, .. }
+        | Expression::FloatLiteral { token, .. }
+        | Expression::BooleanLiteral { token, .. } => token.literal.clone(),
+        Expression::CharLiteral { value, .. } => lexer::char_source(*value),
+        Expression::Prefix { token, right } => {
+            let mut operand = format_operand(right, config, |p| p < Precedence::Prefix);
+            // `- -x` must not become `--x`, which lexes as a decrement
+            if operand.starts_with(&token.literal) {
+                operand = format!("({})", operand);
+            }
+            format!("{}{}", token.literal, operand)
+        }
+        Expression::Infix { token, left, right } => {
+            let precedence = token.typ.precedence();
+            // operators are left-associative, except that comparisons can't chain at all
+            let left = format_operand(left, config, |p| {
+                p < precedence || (p == precedence && precedence == Precedence::LessGreater)
+            });
+            let right = format_operand(right, config, |p| p <= precedence);
+
+            // `a - -b` must not become `a--b`, which lexes as a decrement
+            if config.spaces_around_operators || right.starts_with(['+', '-']) {
+                format!("{} {} {}", left, token.literal, right)
+            } else {
+                format!("{}{}{}", left, token.literal, right)
+            }
+        }
+        Expression::Postfix { token, left } => {
+            let operand = format_operand(left, config, |p| p < Precedence::Postfix);
+            format!("{}{}", operand, token.literal)
+        }
+    }
+}
+
+/// Formats `expression`, parenthesised if `needs_parens` says its precedence is too low
+/// for where it appears
+fn format_operand(
+    expression: &Expression,
+    config: &FormatConfig,
+    needs_parens: impl Fn(Precedence) -> bool,
+) -> String {
+    let formatted = format_expression(expression, config);
+    if precedence(expression).is_some_and(needs_parens) {
+        format!("({})", formatted)
+    } else {
+        formatted
+    }
+}
+
+/// The precedence of an operator expression; `None` for atoms, which never need parens
+fn precedence(expression: &Expression) -> Option<Precedence> {
+    match expression {
+        Expression::Prefix { .. } => Some(Precedence::Prefix),
+        Expression::Infix { token, .. } => Some(token.typ.precedence()),
+        Expression::Postfix { .. } => Some(Precedence::Postfix),
+        _ => None,
+    }
+}
+
+/// The first and last source lines a statement covers, including its doc comment
+fn line_range(statement: &Statement) -> (usize, usize) {
+    match statement {
+        Statement::LetStatement {
+            token, value, doc, ..
+        } => {
+            let doc_lines = doc.as_ref().map_or(0, |d| d.split('\n').count());
+            let start = token.loc.line.saturating_sub(doc_lines);
+            let end = last_loc(value).map_or(token.loc.line, |loc| loc.line);
+            (start, end.max(token.loc.line))
+        }
+    }
+}
+
+fn last_loc(expression: &Expression) -> Option<TokenLoc> {
+    match expression {
+        Expression::Dummy => None,
+        Expression::Identifier(identifier) => Some(identifier.token.loc),
+        Expression::IntegerLiteral { token, .. }
+        | Expression::FloatLiteral { token, .. }
+        | Expression::BooleanLiteral { token, .. }
+        | Expression::CharLiteral { token, .. } => Some(token.loc),
+        Expression::Prefix { token, right } => last_loc(right).or(Some(token.loc)),
+        Expression::Infix { token, right, .. } => last_loc(right).or(Some(token.loc)),
+        Expression::Postfix { token, .. } => Some(token.loc),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FormatConfig, FormatError};
+    use crate::config;
+
+    fn format_source(src: &str) -> Result<String, FormatError> {
+        super::format_source(src, &FormatConfig::default())
+    }
+
+    #[test]
+    fn test_format_source() {
+        let src = "#!/usr/bin/env monkey
+let   a=1+2*3 ;let b : int=( a+1 )*-( 2 ) ;
+
+
+///   Docs keep their text.
+///
+let c = ((a < b) == (b > c)) != !(x - (y - z));
+let d=- -1;let e = -x++ - (1 + 2) - 3;
+let f='\\t' ;
+";
+
+        let expected = "#!/usr/bin/env monkey
+let a = 1 + 2 * 3;
+let b: int = (a + 1) * -2;
+
+///   Docs keep their text.
+///
+let c = a < b == b > c != !(x - (y - z));
+let d = -(-1);
+let e = -x++ - (1 + 2) - 3;
+let f = '\\t';
+";
+
+        let formatted = format_source(src).expect("should format");
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_keeps_required_parens() {
+        for src in [
+            "let a = (a < b) > c;\n",
+            "let a = a - (b - c);\n",
+            "let a = -(-a);\n",
+        ] {
+            assert_eq!(format_source(src).unwrap(), src);
+        }
+    }
+
+    #[test]
+    fn test_format_config() {
+        let table = config::parse("[fmt]\nspaces_around_operators = false").unwrap();
+        let config = FormatConfig::from_table(&table).unwrap();
+
+        let formatted = super::format_source("let a = (1 + b) * c - -d < e;", &config);
+        assert_eq!(formatted.unwrap(), "let a = (1+b)*c - -d<e;\n");
+
+        let table = config::parse("[fmt]\nindent = 2").unwrap();
+        assert_eq!(
+            FormatConfig::from_table(&table),
+            Err("unknown formatter setting `fmt.indent`".into())
+        );
+    }
+
+    #[test]
+    fn test_format_errors() {
+        assert!(matches!(
+            format_source("let a = 1; // note"),
+            Err(FormatError::Comments)
+        ));
+        assert!(matches!(
+            format_source("let a = /* note */ 1;"),
+            Err(FormatError::Comments)
+        ));
+        assert!(matches!(
+            format_source("let a = 1;\n/// trailing doc\n"),
+            Err(FormatError::Comments)
+        ));
+        assert!(matches!(
+            format_source("let o = /// x\n 1;"),
+            Err(FormatError::Comments)
+        ));
+        assert!(matches!(
+            format_source("let a = ;"),
+            Err(FormatError::Parse(_))
+        ));
+    }
+}
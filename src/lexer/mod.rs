@@ -1,25 +1,61 @@
 pub mod tokens;
 
-use std::{fmt::Write, iter::Peekable, str::Chars};
-
-use tokens::{Token, TokenType};
+use tokens::{Token, TokenLoc, TokenType};
 
 pub struct Lexer<'a> {
-    iter: Peekable<Chars<'a>>,
+    src: &'a str,
+    /// byte offset of `curr` in `src`
+    pos: usize,
+    /// byte offset of the character after `curr`
+    next_pos: usize,
     curr: char,
     complete: bool,
     line: usize,
     col: usize,
+    /// byte offset of `src` in the whole source, for fragments
+    base_offset: usize,
+    /// byte offset of the start of the token being lexed
+    token_start: usize,
+    /// whether a `//`, `#` or `/* */` comment has been skipped
+    skipped_comment: bool,
+    /// whether `#` starts a line comment, as well as `//`
+    hash_comments: bool,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(src: &'a str) -> Self {
+        let mut l = Lexer::with_position(
+            src,
+            TokenLoc {
+                line: 1,
+                col: 0,
+                offset: 0,
+            },
+        );
+
+        // skip a `#!/usr/bin/env monkey` line so scripts can be executed directly
+        if l.curr == '#' && l.peek_char() == '!' {
+            l.skip_to_next_line();
+        }
+
+        l
+    }
+
+    /// Lexes `src` as a fragment of a larger source, reporting token locations as if `src`
+    /// began at `start`
+    pub fn with_position(src: &'a str, start: TokenLoc) -> Self {
         let mut l = Lexer {
-            iter: src.chars().peekable(),
+            src,
+            pos: 0,
+            next_pos: 0,
             curr: '\0',
             complete: false,
-            line: 1,
-            col: 0,
+            line: start.line,
+            col: start.col,
+            base_offset: start.offset,
+            token_start: 0,
+            skipped_comment: false,
+            hash_comments: false,
         };
 
         l.read_char();
@@ -27,6 +63,18 @@ impl<'a> Lexer<'a> {
         l
     }
 
+    /// Also treats `#` as starting a line comment, for shell- or config-file-style sources
+    pub fn with_hash_comments(mut self, enabled: bool) -> Self {
+        self.hash_comments = enabled;
+        self
+    }
+
+    /// Whether any ordinary (non-doc) comments have been lexed so far. They produce no
+    /// tokens, so tools that regenerate source from tokens or the AST would lose them.
+    pub fn skipped_comment(&self) -> bool {
+        self.skipped_comment
+    }
+
     fn skip_whitespace(&mut self) {
         while self.curr.is_ascii_whitespace() {
             self.read_char()
@@ -39,23 +87,61 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Skips a (possibly nested) `/* ... */` comment, returning an Illegal token if the
+    /// input ends before the comment is closed
+    fn skip_block_comment(&mut self) -> Option<tokens::Token> {
+        let (line, col) = (self.line, self.col - 1);
+
+        // consume the opening "/*"
+        self.read_char();
+        self.read_char();
+
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.curr, self.peek_char()) {
+                ('\0', _) => {
+                    return Some(Token::illegal(
+                        "/*",
+                        "unterminated block comment",
+                        line,
+                        col,
+                    ))
+                }
+                ('/', '*') => {
+                    depth += 1;
+                    self.read_char();
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    self.read_char();
+                }
+                _ => {}
+            }
+            self.read_char();
+        }
+
+        None
+    }
+
     fn next_token(&mut self) -> tokens::Token {
         use tokens::TokenType::*;
 
         self.skip_whitespace();
+        self.token_start = self.pos;
 
         if self.curr == '\0' {
             self.complete = true;
-            return self.new_token(EOF, "");
+            // `col` has moved past the end, so EOF is placed just after the last character
+            return Token::new(EOF, "", self.line, self.col - 1);
         }
 
-        let mut literal: String = self.curr.into();
+        let src = self.src;
+        let start = self.pos;
 
         let typ = match self.curr {
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
-                    literal = "==".into();
                     EqualEqual
                 } else {
                     Assign
@@ -64,7 +150,6 @@ impl<'a> Lexer<'a> {
             '!' => {
                 if self.peek_char() == '=' {
                     self.read_char();
-                    literal = "!=".into();
                     NotEqual
                 } else {
                     Not
@@ -73,7 +158,6 @@ impl<'a> Lexer<'a> {
             '>' => {
                 if self.peek_char() == '=' {
                     self.read_char();
-                    literal = ">=".into();
                     GreaterEqual
                 } else {
                     Greater
@@ -82,35 +166,67 @@ impl<'a> Lexer<'a> {
             '<' => {
                 if self.peek_char() == '=' {
                     self.read_char();
-                    literal = "<=".into();
                     LessEqual
                 } else {
                     Less
                 }
             }
-            '/' => {
-                if self.peek_char() == '/' {
+            '/' => match self.peek_char() {
+                '/' if self.char_at(self.next_pos + 1) == '/'
+                    && self.char_at(self.next_pos + 2) != '/' =>
+                {
+                    return self.read_doc_comment();
+                }
+                '/' => {
                     // line comment
+                    self.skipped_comment = true;
                     self.skip_to_next_line();
                     return self.next_token();
-                } else {
-                    Divide
                 }
+                '*' => {
+                    self.skipped_comment = true;
+                    if let Some(t) = self.skip_block_comment() {
+                        return t;
+                    }
+                    return self.next_token();
+                }
+                _ => Divide,
+            },
+            '#' if self.hash_comments => {
+                self.skipped_comment = true;
+                self.skip_to_next_line();
+                return self.next_token();
             }
             ',' => Comma,
             ';' => SemiColon,
-            '+' => Plus,
-            '-' => Subtract,
+            ':' => Colon,
+            '+' => {
+                if self.peek_char() == '+' {
+                    self.read_char();
+                    Increment
+                } else {
+                    Plus
+                }
+            }
+            '-' => {
+                if self.peek_char() == '-' {
+                    self.read_char();
+                    Decrement
+                } else {
+                    Subtract
+                }
+            }
             '*' => Multiply,
             '{' => LBrace,
             '}' => RBrace,
             '(' => LParen,
             ')' => RParen,
+            '\'' => return self.read_char_literal(),
             c if c.is_ascii_digit() || c == '.' => return self.read_number(),
             _ => return self.read_identifier(),
         };
 
-        let tok = self.new_token(typ, &literal);
+        let tok = self.new_token(typ, &src[start..self.next_pos]);
 
         self.read_char();
 
@@ -118,71 +234,129 @@ impl<'a> Lexer<'a> {
     }
 
     fn read_number(&mut self) -> tokens::Token {
+        let src = self.src;
+        let start = self.pos;
+        let (line, col) = (self.line, self.col - 1);
         let mut has_point = false;
-        let mut literal = String::new();
 
         while self.curr.is_ascii_digit() || self.curr == '.' {
             if self.curr == '.' {
                 if has_point {
-                    return self.new_token(TokenType::Illegal, &(literal + "."));
+                    return self.new_illegal(
+                        &src[start..self.next_pos],
+                        "multiple decimal points in number",
+                    );
                 }
 
                 has_point = true
             }
 
-            literal
-                .write_char(self.curr)
-                .expect("failed appending to literal string");
+            self.read_char();
+        }
 
-            self.read_char()
+        let has_exponent = self.curr == 'e' || self.curr == 'E';
+        if has_exponent {
+            self.read_char();
+
+            if self.curr == '+' || self.curr == '-' {
+                self.read_char();
+            }
+
+            if !self.curr.is_ascii_digit() {
+                let literal = &src[start..self.pos];
+                return Token::illegal(literal, "missing digits in exponent", line, col);
+            }
+
+            while self.curr.is_ascii_digit() {
+                self.read_char();
+            }
+        }
+
+        if is_identifier_continue(self.curr) {
+            while is_identifier_continue(self.curr) {
+                self.read_char();
+            }
+            let literal = &src[start..self.pos];
+            return Token::illegal(literal, "identifiers cannot start with a digit", line, col);
         }
 
-        if literal.is_empty() {
-            self.new_token(TokenType::Illegal, &self.curr.to_string())
+        let typ = if has_point || has_exponent {
+            TokenType::Float
         } else {
-            let typ = if has_point {
-                TokenType::Float
-            } else {
-                TokenType::Int
-            };
-            let mut t = self.new_token(typ, &literal);
+            TokenType::Int
+        };
+        Token::new(typ, &src[start..self.pos], line, col)
+    }
+
+    fn read_doc_comment(&mut self) -> tokens::Token {
+        let start = self.pos;
+        let (line, col) = (self.line, self.col - 1);
 
-            // subtract 1 from loc for the non-numeric character that terminated the number
-            t.loc.col -= 1;
+        self.skip_to_next_line();
+
+        let literal = self.src[start..self.pos].trim_end_matches('\r');
+        Token::new(TokenType::DocComment, literal, line, col)
+    }
 
-            t
+    fn read_char_literal(&mut self) -> tokens::Token {
+        let src = self.src;
+        let start = self.pos;
+        let (line, col) = (self.line, self.col - 1);
+        self.read_char();
+
+        // collect the source text up to the closing quote, keeping escapes intact
+        let mut escaped = false;
+        loop {
+            match self.curr {
+                '\0' | '\n' => {
+                    let raw = &src[start..self.pos];
+                    return Token::illegal(raw, "unterminated character literal", line, col);
+                }
+                '\'' if !escaped => break,
+                c => {
+                    escaped = c == '\\' && !escaped;
+                    self.read_char();
+                }
+            }
+        }
+        self.read_char();
+        let raw = &src[start..self.pos];
+
+        // the literal keeps the source text so the token's span covers all of it; the
+        // parser decodes it again
+        match char_value(raw) {
+            Ok(_) => Token::new(TokenType::Char, raw, line, col),
+            Err(reason) => Token::illegal(raw, reason, line, col),
         }
     }
 
     fn read_identifier(&mut self) -> tokens::Token {
-        let mut literal = String::new();
-
-        while legal_identifier_char(self.curr) {
-            literal
-                .write_char(self.curr)
-                .expect("failed appending to literal string");
-            self.read_char()
+        if !is_identifier_start(self.curr) {
+            let tok = self.new_illegal(&self.curr.to_string(), "unexpected character");
+            self.read_char();
+            return tok;
         }
 
-        if literal.is_empty() {
-            return self.new_token(TokenType::Illegal, &self.curr.to_string());
+        let src = self.src;
+        let start = self.pos;
+        // the location is taken up front, since reading a terminating newline moves to the
+        // next line
+        let (line, col) = (self.line, self.col - 1);
+
+        while is_identifier_continue(self.curr) {
+            self.read_char();
         }
+        let literal = &src[start..self.pos];
 
         // check if literal is a keyword
-        let mut tok =
-            if let Some(t) = Token::from_keyword(&literal, self.line, self.col - literal.len()) {
-                t
-            } else {
-                self.new_token(TokenType::Identifier, &literal)
-            };
-
-        // subtract 1 from loc for the non-identifier character that terminated the identifier
-        tok.loc.col -= 1;
-        tok
+        Token::from_keyword(literal, line, col)
+            .unwrap_or_else(|| Token::new(TokenType::Identifier, literal, line, col))
     }
 
     fn read_char(&mut self) {
-        self.curr = self.iter.next().unwrap_or('\0');
+        self.pos = self.next_pos;
+        self.curr = self.char_at(self.pos);
+        self.next_pos = (self.pos + self.curr.len_utf8()).min(self.src.len());
         if self.curr == '\n' {
             self.col = 0;
             self.line += 1;
@@ -191,17 +365,86 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn peek_char(&mut self) -> char {
-        *self.iter.peek().unwrap_or(&'\0')
+    fn peek_char(&self) -> char {
+        self.char_at(self.next_pos)
+    }
+
+    /// Decodes the character starting at byte offset `pos`, with a fast path for ASCII
+    fn char_at(&self, pos: usize) -> char {
+        match self.src.as_bytes().get(pos) {
+            Some(&b) if b.is_ascii() => b as char,
+            Some(_) => self.src[pos..].chars().next().unwrap_or('\0'),
+            None => '\0',
+        }
     }
 
     fn new_token(&self, typ: tokens::TokenType, literal: &str) -> tokens::Token {
-        tokens::Token::new(typ, literal, self.line, self.col - literal.len())
+        tokens::Token::new(typ, literal, self.line, self.col - literal.chars().count())
+    }
+
+    fn new_illegal(&self, literal: &str, reason: &'static str) -> tokens::Token {
+        let col = self.col - literal.chars().count();
+        tokens::Token::illegal(literal, reason, self.line, col)
+    }
+}
+
+/// Writes `c` as a character literal that lexes back to it, e.g. `'a'` or `'\n'`
+pub fn char_source(c: char) -> String {
+    let escaped = match c {
+        '\n' => "\\n",
+        '\t' => "\\t",
+        '\r' => "\\r",
+        '\0' => "\\0",
+        '\\' => "\\\\",
+        '\'' => "\\'",
+        _ => return format!("'{}'", c),
+    };
+    format!("'{}'", escaped)
+}
+
+/// Decodes a character literal's source text, quotes included, e.g. `'\n'` to a newline
+pub fn char_value(raw: &str) -> Result<char, &'static str> {
+    let body = raw
+        .strip_prefix('\'')
+        .and_then(|body| body.strip_suffix('\''))
+        .ok_or("unterminated character literal")?;
+
+    let mut value = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        let c = if c == '\\' {
+            match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                Some('\\') => '\\',
+                Some('\'') => '\'',
+                _ => return Err("unknown escape sequence"),
+            }
+        } else {
+            c
+        };
+        value.push(c);
+    }
+
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => Err("empty character literal"),
+        (Some(c), None) => Ok(c),
+        _ => Err("character literal may only contain one character"),
     }
 }
 
-fn legal_identifier_char(c: char) -> bool {
-    c.is_alphanumeric() || c == '_'
+/// Approximates UAX#31 `XID_Start` (plus `_`); std exposes no XID tables, so this uses the
+/// Alphabetic property, which covers letters and letter numbers
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+/// Approximates UAX#31 `XID_Continue`: any identifier start character, or a digit
+fn is_identifier_continue(c: char) -> bool {
+    is_identifier_start(c) || c.is_numeric()
 }
 
 impl<'a> Iterator for Lexer<'a> {
@@ -209,7 +452,9 @@ impl<'a> Iterator for Lexer<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         if !self.complete {
-            Some(self.next_token())
+            let mut token = self.next_token();
+            token.loc.offset = self.base_offset + self.token_start;
+            Some(token)
         } else {
             None
         }
@@ -218,9 +463,26 @@ impl<'a> Iterator for Lexer<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::tokens::{Token, TokenType::*};
+    use super::tokens::{Token, TokenType::*, KEYWORDS};
     use super::Lexer;
 
+    /// Collects `tokens`, checking that each one's offset into `input` agrees with its line
+    /// and column before clearing it, so expectations can be written with `Token::new`
+    fn lex(input: &str, tokens: impl Iterator<Item = Token>) -> Vec<Token> {
+        tokens
+            .map(|mut t| {
+                let before = &input[..t.loc.offset];
+                let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+                let line = before.matches('\n').count() + 1;
+                let col = before[line_start..].chars().count();
+                assert_eq!((line, col), (t.loc.line, t.loc.col), "offset of {:?}", t);
+
+                t.loc.offset = 0;
+                t
+            })
+            .collect()
+    }
+
     #[test]
     fn test_next_token() {
         let input = "let five = 5;
@@ -326,7 +588,7 @@ mod tests {
             Token::new(Int, "50", 17, 29),
             Token::new(SemiColon, ";", 17, 31),
             // the comments
-            Token::new(EOF, "", 19, 38),
+            Token::new(EOF, "", 19, 37),
         ];
 
         for i in expected.into_iter() {
@@ -335,4 +597,215 @@ mod tests {
 
         assert!(l.complete, "expected no more tokens")
     }
+
+    #[test]
+    fn test_scientific_notation() {
+        let input = "1e9 2.5e-3 1E+6 1e 1e+ ";
+
+        let expected = vec![
+            Token::new(Float, "1e9", 1, 0),
+            Token::new(Float, "2.5e-3", 1, 4),
+            Token::new(Float, "1E+6", 1, 11),
+            Token::illegal("1e", "missing digits in exponent", 1, 16),
+            Token::illegal("1e+", "missing digits in exponent", 1, 19),
+            Token::new(EOF, "", 1, 23),
+        ];
+
+        let tokens = lex(input, Lexer::new(input));
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let input = "1 /* a
+        multi-line /* nested */ comment */ 2 /* unterminated";
+
+        let expected = vec![
+            Token::new(Int, "1", 1, 0),
+            Token::new(Int, "2", 2, 43),
+            Token::illegal("/*", "unterminated block comment", 2, 45),
+            Token::new(EOF, "", 2, 60),
+        ];
+
+        let tokens = lex(input, Lexer::new(input));
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let input = r"'a' '\n' '\'' 'ab' '' '\q' 'x";
+
+        let expected = vec![
+            Token::new(Char, "'a'", 1, 0),
+            Token::new(Char, r"'\n'", 1, 4),
+            Token::new(Char, r"'\''", 1, 9),
+            Token::illegal(
+                "'ab'",
+                "character literal may only contain one character",
+                1,
+                14,
+            ),
+            Token::illegal("''", "empty character literal", 1, 19),
+            Token::illegal(r"'\q'", "unknown escape sequence", 1, 22),
+            Token::illegal("'x", "unterminated character literal", 1, 27),
+            Token::new(EOF, "", 1, 29),
+        ];
+
+        let tokens = lex(input, Lexer::new(input));
+        assert_eq!(expected, tokens);
+
+        // spans cover the whole literal, quotes and escapes included
+        assert_eq!(tokens[1].span().end.col, 8);
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let input = "_private π_2 naïve 1abc 2.5x $ ";
+
+        let expected = vec![
+            Token::new(Identifier, "_private", 1, 0),
+            Token::new(Identifier, "π_2", 1, 9),
+            Token::new(Identifier, "naïve", 1, 13),
+            Token::illegal("1abc", "identifiers cannot start with a digit", 1, 19),
+            Token::illegal("2.5x", "identifiers cannot start with a digit", 1, 24),
+            Token::illegal("$", "unexpected character", 1, 29),
+            Token::new(EOF, "", 1, 31),
+        ];
+
+        let tokens = lex(input, Lexer::new(input));
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_keywords() {
+        for (i, &(keyword, typ)) in KEYWORDS.iter().enumerate() {
+            assert!(
+                KEYWORDS[..i].iter().all(|&(k, t)| k != keyword && t != typ),
+                "{:?} is registered twice",
+                keyword
+            );
+            assert!(
+                keyword.chars().all(super::is_identifier_continue),
+                "{:?} can never be lexed",
+                keyword
+            );
+
+            let tokens = lex(keyword, Lexer::new(keyword));
+            assert_eq!(tokens[0], Token::new(typ, keyword, 1, 0));
+        }
+    }
+
+    #[test]
+    fn test_token_type_predicates() {
+        use super::tokens::Precedence;
+
+        assert!(Multiply.precedence() > Plus.precedence());
+        assert_eq!(Increment.precedence(), Precedence::Postfix);
+        assert_eq!(Identifier.precedence(), Precedence::Lowest);
+
+        assert!(Assign.is_operator() && Decrement.is_operator() && !Comma.is_operator());
+        assert!(Let.is_keyword() && True.is_keyword() && !Identifier.is_keyword());
+        assert!(Float.is_literal() && False.is_literal() && !Identifier.is_literal());
+    }
+
+    #[test]
+    fn test_shebang() {
+        let input = "#!/usr/bin/env monkey
+let x = 1;";
+
+        let mut l = Lexer::new(input);
+        assert_eq!(Token::new(Let, "let", 2, 0), l.next_token());
+
+        // only a leading shebang is skipped
+        let mut l = Lexer::new(" #!");
+        assert_eq!(
+            Token::illegal("#", "unexpected character", 1, 1),
+            l.next_token()
+        );
+    }
+
+    #[test]
+    fn test_tokens_at_end_of_line() {
+        let input = "let x\n1\n1.5\n1e";
+
+        let expected = vec![
+            Token::new(Let, "let", 1, 0),
+            Token::new(Identifier, "x", 1, 4),
+            Token::new(Int, "1", 2, 0),
+            Token::new(Float, "1.5", 3, 0),
+            Token::illegal("1e", "missing digits in exponent", 4, 0),
+        ];
+
+        let tokens = lex(input, Lexer::new(input).take(5));
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_eof_location() {
+        // EOF sits just after the last character of the input
+        for (input, line, col) in [("", 1, 0), ("let", 1, 3), ("let x;\n", 2, 0), ("é", 1, 1)] {
+            let eof = Lexer::new(input).last().unwrap();
+            assert_eq!(eof.loc.offset, input.len(), "input {:?}", input);
+            assert_eq!(
+                lex(input, [eof].into_iter()),
+                [Token::new(EOF, "", line, col)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_hash_comments() {
+        let input = "#!/usr/bin/env monkey\n# settings\nlet x = 1; # one\n// two";
+
+        let expected = vec![
+            Token::new(Let, "let", 3, 0),
+            Token::new(Identifier, "x", 3, 4),
+            Token::new(Assign, "=", 3, 6),
+            Token::new(Int, "1", 3, 8),
+            Token::new(SemiColon, ";", 3, 9),
+            Token::new(EOF, "", 4, 6),
+        ];
+
+        let mut lexer = Lexer::new(input).with_hash_comments(true);
+        let tokens = lex(input, lexer.by_ref());
+        assert_eq!(expected, tokens);
+        assert!(lexer.skipped_comment());
+
+        // without the option `#` is just an unexpected character
+        let tokens = lex("# x", Lexer::new("# x"));
+        assert_eq!(tokens[0], Token::illegal("#", "unexpected character", 1, 0));
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let input = "/// adds one\n// not docs\n//// not docs either\nlet /// trailing";
+
+        let expected = vec![
+            Token::new(DocComment, "/// adds one", 1, 0),
+            Token::new(Let, "let", 4, 0),
+            Token::new(DocComment, "/// trailing", 4, 4),
+        ];
+
+        let tokens = lex(input, Lexer::new(input).take(3));
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn test_increment_decrement() {
+        let input = "x++ - -y--+1";
+
+        let expected = vec![
+            Token::new(Identifier, "x", 1, 0),
+            Token::new(Increment, "++", 1, 1),
+            Token::new(Subtract, "-", 1, 4),
+            Token::new(Subtract, "-", 1, 6),
+            Token::new(Identifier, "y", 1, 7),
+            Token::new(Decrement, "--", 1, 8),
+            Token::new(Plus, "+", 1, 10),
+            Token::new(Int, "1", 1, 11),
+        ];
+
+        let tokens = lex(input, Lexer::new(input).take(8));
+        assert_eq!(expected, tokens);
+    }
 }
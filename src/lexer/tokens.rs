@@ -1,3 +1,4 @@
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum TokenType {
     Illegal,
@@ -7,6 +8,9 @@ pub enum TokenType {
     Identifier,
     Int,
     Float,
+    Char,
+    /// a `/// ...` comment, whose literal is the whole line
+    DocComment,
 
     // Operators
     Assign,
@@ -21,10 +25,13 @@ pub enum TokenType {
     Not,
     EqualEqual,
     NotEqual,
+    Increment,
+    Decrement,
 
     // Delimeters
     Comma,
     SemiColon,
+    Colon,
 
     // Brackets
     LParen,
@@ -42,10 +49,93 @@ pub enum TokenType {
     Return,
 }
 
+/// Binding power of operators, from loosest to tightest
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Lowest,
+    Equals,
+    LessGreater,
+    Sum,
+    Product,
+    Prefix,
+    Postfix,
+}
+
+impl TokenType {
+    /// The precedence of the token when it follows an expression; `Lowest` for anything
+    /// that isn't an infix or postfix operator
+    pub fn precedence(self) -> Precedence {
+        use TokenType::*;
+
+        match self {
+            EqualEqual | NotEqual => Precedence::Equals,
+            Greater | GreaterEqual | Less | LessEqual => Precedence::LessGreater,
+            Plus | Subtract => Precedence::Sum,
+            Multiply | Divide => Precedence::Product,
+            Increment | Decrement => Precedence::Postfix,
+            _ => Precedence::Lowest,
+        }
+    }
+
+    pub fn is_operator(self) -> bool {
+        use TokenType::*;
+
+        matches!(
+            self,
+            Assign
+                | Plus
+                | Subtract
+                | Divide
+                | Multiply
+                | Greater
+                | GreaterEqual
+                | Less
+                | LessEqual
+                | Not
+                | EqualEqual
+                | NotEqual
+                | Increment
+                | Decrement
+        )
+    }
+
+    pub fn is_keyword(self) -> bool {
+        KEYWORDS.iter().any(|&(_, typ)| typ == self)
+    }
+
+    /// Whether the token is a literal value; `true` and `false` are keywords as well
+    pub fn is_literal(self) -> bool {
+        use TokenType::*;
+
+        matches!(self, Int | Float | Char | True | False)
+    }
+}
+
+/// Every reserved word and the token it lexes as. A new keyword only needs adding here;
+/// note that it stops the word being usable as a name.
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("let", TokenType::Let),
+    ("fn", TokenType::Function),
+    ("if", TokenType::If),
+    ("else", TokenType::Else),
+    ("return", TokenType::Return),
+    ("true", TokenType::True),
+    ("false", TokenType::False),
+];
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct TokenLoc {
     pub line: usize,
     pub col: usize,
+    /// Byte offset into the source
+    pub offset: usize,
+}
+
+/// A source range from `start` up to (but not including) `end`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: TokenLoc,
+    pub end: TokenLoc,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -53,28 +143,47 @@ pub struct Token {
     pub typ: TokenType,
     pub literal: String,
     pub loc: TokenLoc,
+    /// Why the lexer rejected this token; only set for `TokenType::Illegal`
+    pub reason: Option<&'static str>,
 }
 
 impl Token {
+    /// A token at the given line and column. Its offset is 0 until the lexer fills it in,
+    /// as only the lexer knows where the line starts.
     pub fn new(typ: TokenType, lit: &str, line: usize, col: usize) -> Self {
         Self {
             typ,
             literal: lit.into(),
-            loc: TokenLoc { line, col },
+            loc: TokenLoc {
+                line,
+                col,
+                offset: 0,
+            },
+            reason: None,
+        }
+    }
+
+    pub fn illegal(lit: &str, reason: &'static str, line: usize, col: usize) -> Self {
+        Self {
+            reason: Some(reason),
+            ..Self::new(TokenType::Illegal, lit, line, col)
+        }
+    }
+
+    /// The range of source the token covers, measured from its literal
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.loc,
+            end: TokenLoc {
+                line: self.loc.line,
+                col: self.loc.col + self.literal.chars().count(),
+                offset: self.loc.offset + self.literal.len(),
+            },
         }
     }
 
     pub fn from_keyword(s: &str, line: usize, col: usize) -> Option<Self> {
-        let typ = match s {
-            "let" => TokenType::Let,
-            "fn" => TokenType::Function,
-            "if" => TokenType::If,
-            "else" => TokenType::Else,
-            "return" => TokenType::Return,
-            "true" => TokenType::True,
-            "false" => TokenType::False,
-            _ => return None,
-        };
+        let &(_, typ) = KEYWORDS.iter().find(|(keyword, _)| *keyword == s)?;
 
         Some(Token::new(typ, s, line, col))
     }
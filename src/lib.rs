@@ -0,0 +1,6 @@
+pub mod config;
+pub mod diagnostic;
+pub mod fmt;
+pub mod lexer;
+pub mod parser;
+pub mod repl;
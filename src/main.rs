@@ -1,10 +1,291 @@
-mod lexer;
-mod parser;
-mod repl;
+mod cli;
+
+use cli::{Command, EXIT_CONFIG_ERROR, EXIT_IO_ERROR, EXIT_PARSE_ERROR, EXIT_SUCCESS, EXIT_USAGE};
+use monkey_rs::{config, diagnostic, lexer, parser, repl};
+
+use std::io::{IsTerminal, Read, Write};
 
 fn main() {
-    let stdin_raw = std::io::stdin();
-    let mut stdin = stdin_raw.lock();
-    let mut stdout = std::io::stdout();
-    repl::start(&mut stdin, &mut stdout)
+    let cli = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(EXIT_USAGE)
+        }
+    };
+
+    // `--color` overrides `MONKEY_COLOR`; by default diagnostics are coloured when stderr,
+    // where they're printed, is a terminal
+    let color = cli
+        .color
+        .or_else(|| diagnostic::color_choice(std::env::var("MONKEY_COLOR").ok().as_deref()))
+        .unwrap_or_else(|| std::io::stderr().is_terminal());
+
+    let command = cli.command.unwrap_or(if std::io::stdin().is_terminal() {
+        Command::Repl
+    } else {
+        // piped input is a program, not an interactive session
+        Command::Run {
+            path: Some("-".into()),
+            watch: false,
+            args: vec![],
+        }
+    });
+
+    match command {
+        Command::Help => print!("{}", cli::USAGE),
+        Command::DumpAst { format, path } => dump_ast(&format, path.as_ref(), color),
+        Command::Eval(src) => std::process::exit(run(&src, "<eval>", color)),
+        // the script's arguments are ignored until there's an evaluator to pass them to
+        Command::Run {
+            path,
+            watch: watching,
+            args: _,
+        } => {
+            let path = path.or_else(project_entry).unwrap_or_else(|| "-".into());
+            match (path.as_str(), watching) {
+                ("-", true) => {
+                    eprintln!("error: --watch needs a script, and there's no project entry file");
+                    std::process::exit(EXIT_USAGE)
+                }
+                ("-", false) => std::process::exit(run(&read_stdin(), "<stdin>", color)),
+                (path, true) => watch(path, color),
+                (path, false) => std::process::exit(run(&read_file(path), path, color)),
+            }
+        }
+        Command::Doc(path) => std::process::exit(doc(&path, color)),
+        Command::Fmt { check, paths } => std::process::exit(fmt(check, &paths, color)),
+        Command::JsonRepl => {
+            let stdin_raw = std::io::stdin();
+            let mut stdin = stdin_raw.lock();
+            let mut stdout = std::io::stdout();
+            repl::start_json(&mut stdin, &mut stdout)
+        }
+        Command::Repl => {
+            // the REPL prints its diagnostics to stdout, so it makes its own terminal check
+            let mut config = repl::ReplConfig::from_env();
+            if let Some(color) = cli.color {
+                config.color = color;
+            }
+
+            let stdin_raw = std::io::stdin();
+            let mut stdin = stdin_raw.lock();
+            let mut stdout = std::io::stdout();
+            repl::start(&mut stdin, &mut stdout, &config)
+        }
+    }
+}
+
+/// The entry file named by the `monkey.toml` of the project containing the working
+/// directory, if any
+fn project_entry() -> Option<String> {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let project = config::load(&cwd).and_then(|project| match project {
+        Some((path, table)) => config::Project::from_table(&path, &table).map(Some),
+        None => Ok(None),
+    });
+
+    match project {
+        Ok(project) => project?
+            .entry
+            .map(|entry| entry.to_string_lossy().into_owned()),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(EXIT_CONFIG_ERROR)
+        }
+    }
+}
+
+/// Parses `src` as a whole program, reporting any errors, and returns the process exit code
+fn run(src: &str, file_name: &str, color: bool) -> i32 {
+    let program = parser::Parser::new(lexer::Lexer::new(src))
+        .with_file_name(file_name)
+        .parse_checked();
+
+    match program {
+        Ok(program) => {
+            eprint!("{}", program.diagnostics().render(color));
+            EXIT_SUCCESS
+        }
+        Err(diagnostics) => {
+            eprint!("{}", diagnostics.render(color));
+            EXIT_PARSE_ERROR
+        }
+    }
+}
+
+/// Prints the doc comments of the script at `path` as Markdown, returning the exit code
+fn doc(path: &str, color: bool) -> i32 {
+    let src = read_file(path);
+    let program = match parser::Parser::new(lexer::Lexer::new(&src))
+        .with_file_name(path)
+        .parse_checked()
+    {
+        Ok(program) => program,
+        Err(diagnostics) => {
+            eprint!("{}", diagnostics.render(color));
+            return EXIT_PARSE_ERROR;
+        }
+    };
+
+    print!("{}", program.docs_markdown(path));
+    EXIT_SUCCESS
+}
+
+/// Formats each of `paths` in place, or stdin to stdout if there are none. With `check`,
+/// only reports the sources that would change.
+fn fmt(check: bool, paths: &[String], color: bool) -> i32 {
+    let cwd = std::env::current_dir().unwrap_or_default();
+
+    if paths.is_empty() {
+        let config = format_config(&cwd);
+        let src = read_stdin();
+        return match format_reporting_errors(&src, "<stdin>", &config, color) {
+            Some(formatted) if check && formatted != src => {
+                println!("<stdin>");
+                EXIT_PARSE_ERROR
+            }
+            Some(_) if check => EXIT_SUCCESS,
+            Some(formatted) => {
+                print!("{}", formatted);
+                EXIT_SUCCESS
+            }
+            None => EXIT_PARSE_ERROR,
+        };
+    }
+
+    let mut status = EXIT_SUCCESS;
+    for path in paths {
+        // each file is formatted with the settings of the project it belongs to
+        let config = format_config(cwd.join(path).parent().unwrap_or(&cwd));
+        let src = read_file(path);
+        match format_reporting_errors(&src, path, &config, color) {
+            Some(formatted) if formatted == src => {}
+            Some(_) if check => {
+                println!("{}", path);
+                status = EXIT_PARSE_ERROR;
+            }
+            Some(formatted) => {
+                if let Err(e) = std::fs::write(path, formatted) {
+                    eprintln!("error: failed to write {}: {}", path, e);
+                    std::process::exit(EXIT_IO_ERROR)
+                }
+            }
+            None => status = EXIT_PARSE_ERROR,
+        }
+    }
+    status
+}
+
+/// The `[fmt]` settings of the project containing `dir`, or the defaults outside a project
+fn format_config(dir: &std::path::Path) -> monkey_rs::fmt::FormatConfig {
+    let config = config::load(dir).and_then(|project| match project {
+        Some((_, table)) => monkey_rs::fmt::FormatConfig::from_table(&table),
+        None => Ok(Default::default()),
+    });
+    config.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(EXIT_CONFIG_ERROR)
+    })
+}
+
+fn format_reporting_errors(
+    src: &str,
+    file_name: &str,
+    config: &monkey_rs::fmt::FormatConfig,
+    color: bool,
+) -> Option<String> {
+    match monkey_rs::fmt::format_source(src, config) {
+        Ok(formatted) => Some(formatted),
+        Err(monkey_rs::fmt::FormatError::Parse(mut errors)) => {
+            for e in &mut errors {
+                e.file_name = Some(file_name.into());
+            }
+            eprint!(
+                "{}",
+                diagnostic::Diagnostics::from(&errors[..]).render(color)
+            );
+            None
+        }
+        Err(monkey_rs::fmt::FormatError::Comments) => {
+            eprintln!(
+                "error: {}: can't format a source with // or /* */ comments, or /// comments not attached to a let, as they would be lost",
+                file_name
+            );
+            None
+        }
+    }
+}
+
+/// Re-runs the script at `path` whenever its modification time changes, until interrupted
+fn watch(path: &str, color: bool) -> ! {
+    let modified = || std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let clear_screen = std::io::stdout().is_terminal();
+
+    // `Some(None)` once a run has found the file missing, so that's reported only once
+    let mut last_run = None;
+    loop {
+        let current = Some(modified());
+        if current != last_run {
+            last_run = current;
+
+            // clear the screen so only the latest run's diagnostics are visible. The
+            // diagnostics go to stderr, so stdout is flushed first to keep them after it.
+            if clear_screen {
+                print!("\x1b[2J\x1b[H");
+                std::io::stdout().flush().expect("failed to flush stdout");
+            }
+            match std::fs::read_to_string(path) {
+                Ok(src) => {
+                    if run(&src, path, color) == EXIT_SUCCESS {
+                        println!("{}: ok", path);
+                    }
+                }
+                Err(e) => eprintln!("error: failed to read {}: {}", path, e),
+            }
+            println!("[watching {} for changes]", path);
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+}
+
+fn read_file(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("error: failed to read {}: {}", path, e);
+        std::process::exit(EXIT_IO_ERROR)
+    })
+}
+
+fn read_stdin() -> String {
+    let mut src = String::new();
+    std::io::stdin()
+        .read_to_string(&mut src)
+        .expect("failed to read stdin");
+    src
+}
+
+/// Parses the file at `path` (or stdin) and prints its AST as `sexpr` or `dot`
+fn dump_ast(format: &str, path: Option<&String>, color: bool) {
+    let (src, file_name) = match path {
+        Some(p) => (read_file(p), p.as_str()),
+        None => (read_stdin(), "<stdin>"),
+    };
+
+    let program = parser::Parser::new(lexer::Lexer::new(&src))
+        .with_file_name(file_name)
+        .parse();
+    eprint!("{}", program.diagnostics().render(color));
+
+    match format {
+        "sexpr" => println!("{}", program.to_sexpr()),
+        "dot" => print!("{}", program.to_dot()),
+        _ => {
+            eprintln!(
+                "unknown AST dump format '{}', expected sexpr or dot",
+                format
+            );
+            std::process::exit(EXIT_USAGE)
+        }
+    }
 }
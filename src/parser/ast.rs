@@ -1,10 +1,17 @@
-use super::ParseError;
-use crate::lexer::tokens;
+use super::{symbols::Analysis, ParseError};
+use crate::{diagnostic::Diagnostic, lexer::tokens};
+
+use std::cell::OnceCell;
 
 #[derive(Default)]
 pub struct Program {
     pub statements: Vec<Statement>,
     pub errors: Vec<ParseError>,
+    /// problems that don't stop the program parsing, such as stray semicolons
+    pub warnings: Vec<Diagnostic>,
+    /// the name of the file the program was parsed from, if any
+    pub file_name: Option<String>,
+    pub(crate) analysis: OnceCell<Analysis>,
 }
 
 #[derive(Debug)]
@@ -12,13 +19,51 @@ pub enum Statement {
     LetStatement {
         token: tokens::Token,
         identifier: Identifier,
+        /// the declared type in `let x: int = ...`, if annotated
+        type_annotation: Option<Identifier>,
         value: Expression,
+        /// the `///` comments directly before the statement, without their slashes
+        doc: Option<String>,
     },
 }
 
 #[derive(Debug)]
 pub enum Expression {
     Dummy,
+    Identifier(Identifier),
+    IntegerLiteral {
+        token: tokens::Token,
+        value: i64,
+    },
+    FloatLiteral {
+        token: tokens::Token,
+        value: f64,
+    },
+    BooleanLiteral {
+        token: tokens::Token,
+        value: bool,
+    },
+    CharLiteral {
+        token: tokens::Token,
+        value: char,
+    },
+    Prefix {
+        /// the operator token
+        token: tokens::Token,
+        right: Box<Expression>,
+    },
+    Infix {
+        /// the operator token
+        token: tokens::Token,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    /// `x++` or `x--`, which evaluate to the variable's value before it is updated
+    Postfix {
+        /// the operator token
+        token: tokens::Token,
+        left: Box<Expression>,
+    },
 }
 
 #[derive(Debug)]
@@ -26,3 +71,129 @@ pub struct Identifier {
     pub token: tokens::Token,
     pub value: String,
 }
+
+// AST equality is structural: it compares node kinds and literals but ignores the
+// locations carried by tokens, so trees parsed from differently formatted sources
+// (or built by hand) compare equal.
+
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Statement::LetStatement {
+                    identifier: id_a,
+                    type_annotation: type_a,
+                    value: value_a,
+                    ..
+                },
+                Statement::LetStatement {
+                    identifier: id_b,
+                    type_annotation: type_b,
+                    value: value_b,
+                    ..
+                },
+            ) => id_a == id_b && type_a == type_b && value_a == value_b,
+        }
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        use Expression::*;
+
+        match (self, other) {
+            (Dummy, Dummy) => true,
+            (Identifier(a), Identifier(b)) => a == b,
+            (IntegerLiteral { value: a, .. }, IntegerLiteral { value: b, .. }) => a == b,
+            (FloatLiteral { value: a, .. }, FloatLiteral { value: b, .. }) => a == b,
+            (BooleanLiteral { value: a, .. }, BooleanLiteral { value: b, .. }) => a == b,
+            (CharLiteral { value: a, .. }, CharLiteral { value: b, .. }) => a == b,
+            (
+                Prefix {
+                    token: op_a,
+                    right: right_a,
+                },
+                Prefix {
+                    token: op_b,
+                    right: right_b,
+                },
+            ) => op_a.typ == op_b.typ && right_a == right_b,
+            (
+                Infix {
+                    token: op_a,
+                    left: left_a,
+                    right: right_a,
+                },
+                Infix {
+                    token: op_b,
+                    left: left_b,
+                    right: right_b,
+                },
+            ) => op_a.typ == op_b.typ && left_a == left_b && right_a == right_b,
+            (
+                Postfix {
+                    token: op_a,
+                    left: left_a,
+                },
+                Postfix {
+                    token: op_b,
+                    left: left_b,
+                },
+            ) => op_a.typ == op_b.typ && left_a == left_b,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq for Identifier {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+/// Builds a `Vec<Statement>` from the same S-expression shape that `Program::to_sexpr`
+/// prints, e.g. `ast![(let x (+ 1 y))]`. Tokens get placeholder locations.
+#[cfg(test)]
+macro_rules! ast {
+    (@stmt (let $id:ident $value:tt)) => {
+        $crate::parser::ast::Statement::let_(stringify!($id), ast!(@expr $value))
+    };
+    (@stmt (let $id:ident (type $ty:ident) $value:tt)) => {
+        $crate::parser::ast::Statement::let_typed(
+            stringify!($id),
+            stringify!($ty),
+            ast!(@expr $value),
+        )
+    };
+    (@expr dummy) => {
+        $crate::parser::ast::Expression::Dummy
+    };
+    (@expr $lit:literal) => {
+        $crate::parser::ast::Expression::from($lit)
+    };
+    (@expr $id:ident) => {
+        $crate::parser::ast::Expression::ident(stringify!($id))
+    };
+    (@expr ($left:tt ++)) => {
+        $crate::parser::ast::Expression::postfix(ast!(@expr $left), "++")
+    };
+    (@expr ($left:tt --)) => {
+        $crate::parser::ast::Expression::postfix(ast!(@expr $left), "--")
+    };
+    (@expr ($op:tt $right:tt)) => {
+        $crate::parser::ast::Expression::prefix(stringify!($op), ast!(@expr $right))
+    };
+    (@expr ($op:tt $left:tt $right:tt)) => {
+        $crate::parser::ast::Expression::infix(
+            ast!(@expr $left),
+            stringify!($op),
+            ast!(@expr $right),
+        )
+    };
+    ($($stmt:tt),* $(,)?) => {
+        vec![$(ast!(@stmt $stmt)),*]
+    };
+}
+
+#[cfg(test)]
+pub(crate) use ast;
@@ -0,0 +1,215 @@
+//! Constructors for building ASTs without source text, e.g. for code generation or tests.
+//! Synthesized tokens carry a placeholder location of line 0, column 0.
+
+use super::{
+    ast::{Expression, Identifier, Program, Statement},
+    Precedence,
+};
+use crate::lexer::{
+    self,
+    tokens::{Token, TokenType},
+    Lexer,
+};
+
+fn synthetic(typ: TokenType, literal: &str) -> Token {
+    Token::new(typ, literal, 0, 0)
+}
+
+/// Lexes `op` into a single operator token that `accepts`, panicking with `kind` (e.g.
+/// "an infix") in the message if it is anything else
+fn operator(op: &str, kind: &str, accepts: fn(TokenType) -> bool) -> Token {
+    let mut tokens = Lexer::new(op);
+    match (tokens.next(), tokens.next()) {
+        (
+            Some(t),
+            Some(Token {
+                typ: TokenType::EOF,
+                ..
+            }),
+        ) if accepts(t.typ) => synthetic(t.typ, &t.literal),
+        _ => panic!("'{}' is not {} operator", op, kind),
+    }
+}
+
+impl Identifier {
+    pub fn new(name: &str) -> Self {
+        Identifier {
+            token: synthetic(TokenType::Identifier, name),
+            value: name.into(),
+        }
+    }
+}
+
+impl Program {
+    pub fn new(statements: Vec<Statement>) -> Self {
+        Program {
+            statements,
+            ..Program::default()
+        }
+    }
+}
+
+impl Statement {
+    pub fn let_(name: &str, value: Expression) -> Self {
+        Statement::LetStatement {
+            token: synthetic(TokenType::Let, "let"),
+            identifier: Identifier::new(name),
+            type_annotation: None,
+            value,
+            doc: None,
+        }
+    }
+
+    /// A `let` with a type annotation, as in `let x: int = 5;`
+    pub fn let_typed(name: &str, type_name: &str, value: Expression) -> Self {
+        Statement::LetStatement {
+            token: synthetic(TokenType::Let, "let"),
+            identifier: Identifier::new(name),
+            type_annotation: Some(Identifier::new(type_name)),
+            value,
+            doc: None,
+        }
+    }
+}
+
+impl Expression {
+    pub fn ident(name: &str) -> Self {
+        Expression::Identifier(Identifier::new(name))
+    }
+
+    pub fn int(value: i64) -> Self {
+        Expression::IntegerLiteral {
+            token: synthetic(TokenType::Int, &value.to_string()),
+            value,
+        }
+    }
+
+    pub fn float(value: f64) -> Self {
+        Expression::FloatLiteral {
+            token: synthetic(TokenType::Float, &format!("{:?}", value)),
+            value,
+        }
+    }
+
+    pub fn bool(value: bool) -> Self {
+        let typ = if value {
+            TokenType::True
+        } else {
+            TokenType::False
+        };
+        Expression::BooleanLiteral {
+            token: synthetic(typ, &value.to_string()),
+            value,
+        }
+    }
+
+    pub fn char(value: char) -> Self {
+        Expression::CharLiteral {
+            token: synthetic(TokenType::Char, &lexer::char_source(value)),
+            value,
+        }
+    }
+
+    pub fn prefix(op: &str, right: Expression) -> Self {
+        Expression::Prefix {
+            token: operator(op, "a prefix", |typ| {
+                matches!(typ, TokenType::Not | TokenType::Subtract | TokenType::Plus)
+            }),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn infix(left: Expression, op: &str, right: Expression) -> Self {
+        Expression::Infix {
+            token: operator(op, "an infix", |typ| {
+                matches!(
+                    typ.precedence(),
+                    Precedence::Equals
+                        | Precedence::LessGreater
+                        | Precedence::Sum
+                        | Precedence::Product
+                )
+            }),
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn postfix(left: Expression, op: &str) -> Self {
+        Expression::Postfix {
+            token: operator(op, "a postfix", |typ| {
+                typ.precedence() == Precedence::Postfix
+            }),
+            left: Box::new(left),
+        }
+    }
+}
+
+impl From<i64> for Expression {
+    fn from(value: i64) -> Self {
+        Expression::int(value)
+    }
+}
+
+impl From<f64> for Expression {
+    fn from(value: f64) -> Self {
+        Expression::float(value)
+    }
+}
+
+impl From<bool> for Expression {
+    fn from(value: bool) -> Self {
+        Expression::bool(value)
+    }
+}
+
+impl From<char> for Expression {
+    fn from(value: char) -> Self {
+        Expression::char(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::ast::{ast, Expression, Program, Statement};
+
+    #[test]
+    fn test_builders() {
+        let program = Program::new(vec![Statement::let_(
+            "x",
+            Expression::infix(
+                Expression::prefix("-", Expression::int(5)),
+                "*",
+                Expression::infix(Expression::float(2.5), "<=", Expression::ident("y")),
+            ),
+        )]);
+
+        assert_eq!(program.to_sexpr(), "(program (let x (* (- 5) (<= 2.5 y))))");
+        assert_eq!(program.statements, ast![(let x (* (- 5) (<= 2.5 y)))]);
+        assert_ne!(program.statements, ast![(let x (* (- 5) (< 2.5 y)))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "'let' is not an infix operator")]
+    fn test_invalid_operator() {
+        Expression::infix(Expression::int(1), "let", Expression::int(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "'++' is not an infix operator")]
+    fn test_postfix_operator_as_infix() {
+        Expression::infix(Expression::int(1), "++", Expression::int(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "'*' is not a prefix operator")]
+    fn test_infix_operator_as_prefix() {
+        Expression::prefix("*", Expression::int(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "'!' is not a postfix operator")]
+    fn test_prefix_operator_as_postfix() {
+        Expression::postfix(Expression::int(1), "!");
+    }
+}
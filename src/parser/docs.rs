@@ -0,0 +1,71 @@
+//! Documentation rendered from the `///` comments on a program's bindings.
+
+use super::ast::{Program, Statement};
+
+use std::fmt::Write;
+
+impl Program {
+    /// Renders a Markdown page titled `title` with a section for each top-level binding,
+    /// in source order, containing its doc comment if it has one
+    pub fn docs_markdown(&self, title: &str) -> String {
+        let mut out = format!("# {}\n", title);
+
+        for statement in &self.statements {
+            match statement {
+                Statement::LetStatement {
+                    identifier,
+                    type_annotation,
+                    doc,
+                    ..
+                } => {
+                    let signature = match type_annotation {
+                        Some(type_name) => format!("{}: {}", identifier.value, type_name.value),
+                        None => identifier.value.clone(),
+                    };
+                    write!(out, "\n## `{}`\n", signature).expect("failed writing to string");
+
+                    if let Some(doc) = doc {
+                        write!(out, "\n{}\n", doc).expect("failed writing to string");
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn test_docs_markdown() {
+        let src = "
+/// The answer.
+///
+/// Computed the hard way.
+let answer: int = 6 * 7;
+
+// an ordinary comment
+let other = /// not attached to anything
+  answer;
+";
+        let program = Parser::new(Lexer::new(src)).parse();
+        assert!(program.errors.is_empty());
+
+        assert_eq!(
+            program.docs_markdown("answers.mk"),
+            "# answers.mk
+
+## `answer: int`
+
+The answer.
+
+Computed the hard way.
+
+## `other`
+"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use super::ast::{Expression, Identifier, Program, Statement};
+use crate::lexer;
+
+use std::fmt::Write;
+
+/// A format-agnostic view of the tree that both renderers walk
+struct Node {
+    label: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn leaf(label: &str) -> Self {
+        Node {
+            label: label.into(),
+            children: vec![],
+        }
+    }
+
+    fn branch(label: &str, children: Vec<Node>) -> Self {
+        Node {
+            label: label.into(),
+            children,
+        }
+    }
+}
+
+impl Program {
+    /// Renders the program as an S-expression, e.g. `(program (let x 5))`
+    pub fn to_sexpr(&self) -> String {
+        let mut out = String::new();
+        // the root is a list even when empty, so every dump reads as `(program ...)`
+        write_sexpr_list(&program_node(self), &mut out);
+        out
+    }
+
+    /// Renders the program as a Graphviz DOT digraph
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph ast {\n");
+        let mut next_id = 0;
+        write_dot(&program_node(self), &mut next_id, &mut out);
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn program_node(program: &Program) -> Node {
+    Node::branch(
+        "program",
+        program.statements.iter().map(statement_node).collect(),
+    )
+}
+
+fn statement_node(statement: &Statement) -> Node {
+    match statement {
+        Statement::LetStatement {
+            identifier,
+            type_annotation,
+            value,
+            ..
+        } => {
+            let mut children = vec![identifier_node(identifier)];
+            if let Some(type_name) = type_annotation {
+                children.push(Node::branch("type", vec![identifier_node(type_name)]));
+            }
+            children.push(expression_node(value));
+            Node::branch("let", children)
+        }
+    }
+}
+
+fn expression_node(expression: &Expression) -> Node {
+    match expression {
+        Expression::Dummy => Node::leaf("dummy"),
+        Expression::Identifier(identifier) => identifier_node(identifier),
+        Expression::IntegerLiteral { token, .. }
+        | Expression::FloatLiteral { token, .. }
+        | Expression::BooleanLiteral { token, .. } => Node::leaf(&token.literal),
+        Expression::CharLiteral { value, .. } => Node::leaf(&lexer::char_source(*value)),
+        Expression::Prefix { token, right } => {
+            Node::branch(&token.literal, vec![expression_node(right)])
+        }
+        Expression::Infix { token, left, right } => Node::branch(
+            &token.literal,
+            vec![expression_node(left), expression_node(right)],
+        ),
+        Expression::Postfix { token, left } => {
+            Node::branch(&token.literal, vec![expression_node(left)])
+        }
+    }
+}
+
+fn identifier_node(identifier: &Identifier) -> Node {
+    Node::leaf(&identifier.value)
+}
+
+fn write_sexpr(node: &Node, out: &mut String) {
+    if node.children.is_empty() {
+        out.push_str(&node.label);
+    } else {
+        write_sexpr_list(node, out);
+    }
+}
+
+fn write_sexpr_list(node: &Node, out: &mut String) {
+    write!(out, "({}", node.label).expect("failed writing to string");
+    for child in &node.children {
+        out.push(' ');
+        write_sexpr(child, out);
+    }
+    out.push(')');
+}
+
+/// Writes `node` and its descendants, returning the DOT id assigned to `node`
+fn write_dot(node: &Node, next_id: &mut usize, out: &mut String) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(out, "    n{} [label=\"{}\"];", id, label).expect("failed writing to string");
+
+    for child in &node.children {
+        let child_id = write_dot(child, next_id, out);
+        writeln!(out, "    n{} -> n{};", id, child_id).expect("failed writing to string");
+    }
+
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer, parser::Parser};
+
+    #[test]
+    fn test_dump() {
+        let program = Parser::new(lexer::Lexer::new("let x = 1; let y = 2;")).parse();
+
+        assert_eq!(program.to_sexpr(), "(program (let x 1) (let y 2))");
+
+        let expected_dot = "digraph ast {
+    n0 [label=\"program\"];
+    n1 [label=\"let\"];
+    n2 [label=\"x\"];
+    n1 -> n2;
+    n3 [label=\"1\"];
+    n1 -> n3;
+    n0 -> n1;
+    n4 [label=\"let\"];
+    n5 [label=\"y\"];
+    n4 -> n5;
+    n6 [label=\"2\"];
+    n4 -> n6;
+    n0 -> n4;
+}
+";
+        assert_eq!(program.to_dot(), expected_dot);
+        let program = Parser::new(lexer::Lexer::new("let x: int = 1;")).parse();
+        assert_eq!(program.to_sexpr(), "(program (let x (type int) 1))");
+        let program = Parser::new(lexer::Lexer::new("let c = '\\\\';")).parse();
+        assert_eq!(program.to_sexpr(), "(program (let c '\\\\'))");
+        let program = Parser::new(lexer::Lexer::new("")).parse();
+        assert_eq!(program.to_sexpr(), "(program)");
+    }
+}
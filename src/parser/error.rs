@@ -1,19 +1,74 @@
-use crate::lexer::tokens::TokenLoc;
+use crate::lexer::tokens::Span;
 
 use std::fmt;
 
+/// Stable identifiers for each kind of parse error, so tooling can filter on them
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnexpectedToken,
+    ExpectedToken,
+    UnexpectedEof,
+    IllegalToken,
+    TooManyErrors,
+    InvalidLiteral,
+    ChainedComparison,
+    NestingTooDeep,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedToken => "E0001",
+            ErrorCode::ExpectedToken => "E0002",
+            ErrorCode::UnexpectedEof => "E0003",
+            ErrorCode::IllegalToken => "E0004",
+            ErrorCode::TooManyErrors => "E0005",
+            ErrorCode::InvalidLiteral => "E0006",
+            ErrorCode::ChainedComparison => "E0007",
+            ErrorCode::NestingTooDeep => "E0008",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ParseError {
+    pub code: ErrorCode,
     pub message: String,
-    pub loc: Option<TokenLoc>,
+    pub span: Option<Span>,
+    /// the statement the error was found in, from its first token to the end of the text
+    /// skipped to recover from it, for tools that mark the whole broken region
+    pub statement_span: Option<Span>,
+    pub file_name: Option<String>,
+    pub help: Option<String>,
+}
+
+impl ParseError {
+    pub fn new(code: ErrorCode, message: String, span: Option<Span>) -> Self {
+        ParseError {
+            code,
+            message,
+            span,
+            statement_span: None,
+            file_name: None,
+            help: None,
+        }
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.into());
+        self
+    }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let prefix = if let Some(loc) = self.loc {
-            format!("At line={}, col={}: ", loc.line, loc.col)
-        } else {
-            String::new()
+        let prefix = match (&self.file_name, self.span.map(|s| s.start)) {
+            // `file:line:col` is the form editors and terminals recognise, which counts
+            // columns from 1, so messages without a file count from 1 too
+            (Some(file), Some(loc)) => format!("{}:{}:{}: ", file, loc.line, loc.col + 1),
+            (Some(file), None) => format!("{}: ", file),
+            (None, Some(loc)) => format!("At line={}, col={}: ", loc.line, loc.col + 1),
+            (None, None) => String::new(),
         };
         write!(f, "{}{}", prefix, self.message)
     }
@@ -0,0 +1,366 @@
+//! Re-parsing after an edit without re-parsing the whole source, for editor integration.
+//!
+//! Each statement owns the source from its first token up to the first token of the next
+//! statement. An edit re-lexes and re-parses only the statements whose regions it touches;
+//! statements after it are kept and have their locations shifted. Whenever the edit could
+//! change how the surrounding source lexes or parses, this falls back to a full parse.
+
+use super::{
+    ast::{Expression, Program, Statement},
+    error::ErrorCode,
+    Parser, DEFAULT_MAX_ERRORS,
+};
+use crate::lexer::{
+    tokens::{Token, TokenLoc, TokenType},
+    Lexer,
+};
+
+use std::ops::Range;
+
+/// Replaces the bytes in `range` of the old source with `text`
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn apply(&self, src: &str) -> String {
+        let mut out = String::with_capacity(src.len() + self.text.len());
+        out.push_str(&src[..self.range.start]);
+        out.push_str(&self.text);
+        out.push_str(&src[self.range.end..]);
+        out
+    }
+}
+
+/// Produces the program for `edit.apply(old_src)`, reusing the statements of `previous`
+/// (which must have been parsed from `old_src`) that the edit does not touch
+pub fn reparse(previous: Program, old_src: &str, edit: &TextEdit) -> Program {
+    let new_src = edit.apply(old_src);
+
+    let file_name = previous.file_name.clone();
+    match try_reparse(previous, old_src, &new_src, edit) {
+        Some(program) => program,
+        None => {
+            let mut parser = Parser::new(Lexer::new(&new_src));
+            match file_name {
+                Some(name) => parser.with_file_name(&name).parse(),
+                None => parser.parse(),
+            }
+        }
+    }
+}
+
+fn try_reparse(
+    previous: Program,
+    old_src: &str,
+    new_src: &str,
+    edit: &TextEdit,
+) -> Option<Program> {
+    if previous.statements.is_empty() || !lexically_local(old_src, edit) {
+        return None;
+    }
+
+    // a parse that gave up part way has no statements or errors for the rest of the source
+    // to reuse
+    if previous
+        .errors
+        .iter()
+        .any(|e| e.code == ErrorCode::TooManyErrors)
+    {
+        return None;
+    }
+
+    // statement regions: the first starts at the top of the file so edits to leading
+    // whitespace and comments belong to it
+    let mut starts: Vec<usize> = previous
+        .statements
+        .iter()
+        .map(|s| statement_loc(s).offset)
+        .collect();
+    starts[0] = 0;
+    let region_end = |i: usize| starts.get(i + 1).copied().unwrap_or(old_src.len());
+
+    // an edit touching a region boundary counts as touching both sides
+    let first = (0..starts.len()).find(|&i| region_end(i) >= edit.range.start)?;
+    let last = (first..starts.len())
+        .take_while(|&i| starts[i] <= edit.range.end)
+        .last()?;
+
+    // a statement's doc comments sit in the region before it, so re-parsing either region
+    // alone would lose or keep stale docs
+    let has_doc = |i: usize| {
+        matches!(
+            previous.statements.get(i),
+            Some(Statement::LetStatement { doc: Some(_), .. })
+        )
+    };
+    if (first > 0 && has_doc(first)) || has_doc(last + 1) {
+        return None;
+    }
+
+    let old_start = starts[first];
+    let old_end = region_end(last);
+    let new_end = old_end + edit.text.len() - edit.range.len();
+    let reaches_eof = old_end == old_src.len();
+
+    let region = &new_src[old_start..new_end];
+    let region_lexer = || {
+        if old_start == 0 {
+            Lexer::new(region)
+        } else {
+            Lexer::with_position(region, loc_of(new_src, old_start))
+        }
+    };
+
+    // statements never extend past a semicolon, so a region that ends in one parses the
+    // same on its own as it does in context
+    if !reaches_eof {
+        let last_token = region_lexer().filter(|t| t.typ != TokenType::EOF).last();
+        if !matches!(
+            last_token,
+            Some(Token {
+                typ: TokenType::SemiColon,
+                ..
+            })
+        ) {
+            return None;
+        }
+    }
+
+    let mut parser = Parser::new(region_lexer());
+    if let Some(name) = &previous.file_name {
+        parser = parser.with_file_name(name);
+    }
+    let reparsed = parser.parse();
+
+    let region_start_loc = loc_of(old_src, old_start);
+    let old_end_loc = loc_of(old_src, old_end);
+    let new_end_loc = loc_of(new_src, new_end);
+    let shift = |loc: &mut TokenLoc| {
+        if loc.line == old_end_loc.line {
+            loc.col = loc.col + new_end_loc.col - old_end_loc.col;
+        }
+        loc.line = loc.line + new_end_loc.line - old_end_loc.line;
+        loc.offset = loc.offset + new_end - old_end;
+    };
+
+    let mut program = Program {
+        file_name: previous.file_name,
+        ..Program::default()
+    };
+
+    let mut statements = previous.statements.into_iter();
+    program.statements.extend(statements.by_ref().take(first));
+    program.statements.extend(reparsed.statements);
+    for mut s in statements.skip(last - first + 1) {
+        shift_statement(&mut s, &shift);
+        program.statements.push(s);
+    }
+
+    let mut trailing = vec![];
+    for mut e in previous.errors {
+        match e.span {
+            Some(span) if is_before(span.start, region_start_loc) => program.errors.push(e),
+            Some(ref mut span) if !is_before(span.start, old_end_loc) && !reaches_eof => {
+                shift(&mut span.start);
+                shift(&mut span.end);
+                if let Some(statement_span) = &mut e.statement_span {
+                    shift(&mut statement_span.start);
+                    shift(&mut statement_span.end);
+                }
+                trailing.push(e)
+            }
+            None if !reaches_eof => trailing.push(e),
+            _ => {}
+        }
+    }
+    program.errors.extend(reparsed.errors);
+    program.errors.extend(trailing);
+
+    // a full parse would stop at the error cap, somewhere this can't tell
+    if program.errors.len() >= DEFAULT_MAX_ERRORS {
+        return None;
+    }
+
+    let mut trailing = vec![];
+    for mut w in previous.warnings {
+        match w.span {
+            Some(span) if is_before(span.start, region_start_loc) => program.warnings.push(w),
+            Some(ref mut span) if !is_before(span.start, old_end_loc) => {
+                shift(&mut span.start);
+                shift(&mut span.end);
+                trailing.push(w)
+            }
+            _ => {}
+        }
+    }
+    program.warnings.extend(reparsed.warnings);
+    program.warnings.extend(trailing);
+
+    Some(program)
+}
+
+/// Whether the edit is guaranteed not to change the lexing of source outside it, i.e. it
+/// cannot open or close a comment or character literal
+fn lexically_local(src: &str, edit: &TextEdit) -> bool {
+    let sensitive = |c: char| matches!(c, '/' | '*' | '\'' | '#');
+
+    let before = src[..edit.range.start].chars().next_back();
+    let after = src[edit.range.end..].chars().next();
+
+    !(src[edit.range.clone()].chars().any(sensitive)
+        || edit.text.chars().any(sensitive)
+        || before.is_some_and(sensitive)
+        || after.is_some_and(sensitive))
+}
+
+fn statement_loc(statement: &Statement) -> TokenLoc {
+    match statement {
+        Statement::LetStatement { token, .. } => token.loc,
+    }
+}
+
+fn is_before(a: TokenLoc, b: TokenLoc) -> bool {
+    a.offset < b.offset
+}
+
+/// The location of a byte offset, in the same terms the lexer reports
+fn loc_of(src: &str, offset: usize) -> TokenLoc {
+    let before = &src[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    TokenLoc {
+        line: before.matches('\n').count() + 1,
+        col: before[line_start..].chars().count(),
+        offset,
+    }
+}
+
+fn shift_statement(statement: &mut Statement, shift: &impl Fn(&mut TokenLoc)) {
+    match statement {
+        Statement::LetStatement {
+            token,
+            identifier,
+            type_annotation,
+            value,
+            ..
+        } => {
+            shift(&mut token.loc);
+            shift(&mut identifier.token.loc);
+            if let Some(type_name) = type_annotation {
+                shift(&mut type_name.token.loc);
+            }
+            shift_expression(value, shift)
+        }
+    }
+}
+
+fn shift_expression(expression: &mut Expression, shift: &impl Fn(&mut TokenLoc)) {
+    match expression {
+        Expression::Dummy => {}
+        Expression::Identifier(identifier) => shift(&mut identifier.token.loc),
+        Expression::IntegerLiteral { token, .. }
+        | Expression::FloatLiteral { token, .. }
+        | Expression::BooleanLiteral { token, .. }
+        | Expression::CharLiteral { token, .. } => shift(&mut token.loc),
+        Expression::Prefix { token, right } => {
+            shift(&mut token.loc);
+            shift_expression(right, shift)
+        }
+        Expression::Infix { token, left, right } => {
+            shift(&mut token.loc);
+            shift_expression(left, shift);
+            shift_expression(right, shift)
+        }
+        Expression::Postfix { token, left } => {
+            shift(&mut token.loc);
+            shift_expression(left, shift)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reparse, TextEdit};
+    use crate::{lexer::Lexer, parser::Parser};
+
+    use std::ops::Range;
+
+    #[test]
+    fn test_reparse_matches_full_parse() {
+        let src = "let a = 1;\nlet b = 2; let c = 3;\n\nlet d = 4;";
+
+        let edits = [
+            // rename a binding in the middle
+            (15..16, "bee"),
+            // split a line, shifting everything after it
+            (10..11, "\n\n  "),
+            // join two statements onto one line
+            (21..23, " "),
+            // break a statement so it swallows the next one
+            (18..19, ""),
+            // introduce errors
+            (4..5, ""),
+            (33..34, "= ="),
+            // touch the start and end of the file
+            (0..0, "\n"),
+            (src.len()..src.len(), " let e = 5;"),
+            // edits near comments fall back to a full parse
+            (10..10, "//"),
+        ];
+
+        for (range, text) in edits {
+            assert_reparse_matches(src, range, text);
+        }
+
+        // doc comments belong to the statement after them, not the region they're in
+        let src = "let a = 1;\n/// docs\nlet b = 2;\nlet c = 3;";
+        assert_reparse_matches(src, 24..25, "bee");
+        assert_reparse_matches(src, 15..19, "dogs");
+        assert_reparse_matches(src, 34..35, "cee");
+
+        // errors after the edit are kept, and shifted like statements
+        let src = "let a = 1; let c = 2; let 5 = 3; let d = 4;";
+        assert_reparse_matches(src, 4..5, "aaaa");
+        assert_reparse_matches(src, 4..5, "a\n");
+
+        // the error cap applies to the whole source, not to each region
+        let src = format!("let a = 1;\nlet b = 2;{}", "\nlet 5 = 1;".repeat(20));
+        assert_reparse_matches(&src, 6..7, "");
+        let src = format!("let a = 1;\nlet b = 2;{}", "\nlet 5 = 1;".repeat(19));
+        assert_reparse_matches(&src, 6..7, "");
+        assert_reparse_matches(&src, 15..16, " 2");
+
+        // and so are warnings
+        let src = "let a = 1;; let b = 2;;\n;";
+        assert_reparse_matches(src, 4..5, "aaaa");
+        assert_reparse_matches(src, 16..17, "\n");
+        assert_reparse_matches(src, 22..23, "");
+    }
+
+    fn assert_reparse_matches(src: &str, range: Range<usize>, text: &str) {
+        let edit = TextEdit {
+            range,
+            text: text.into(),
+        };
+        let previous = Parser::new(Lexer::new(src)).parse();
+
+        let incremental = reparse(previous, src, &edit);
+        let full = Parser::new(Lexer::new(&edit.apply(src))).parse();
+
+        // `Debug` shows the docs and locations that `PartialEq` ignores
+        assert_eq!(
+            format!(
+                "{:?} {:?} {:?}",
+                incremental.statements, incremental.errors, incremental.warnings
+            ),
+            format!(
+                "{:?} {:?} {:?}",
+                full.statements, full.errors, full.warnings
+            ),
+            "edit {:?}",
+            edit.range
+        );
+    }
+}
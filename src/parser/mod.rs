@@ -1,63 +1,255 @@
 pub mod ast;
+pub mod build;
+pub mod docs;
+pub mod dump;
 pub mod error;
+pub mod incremental;
+pub mod symbols;
+pub mod transform;
+pub mod visit;
 
-use crate::lexer::{
-    self,
-    tokens::{Token, TokenType},
+use crate::{
+    diagnostic::{Diagnostic, Diagnostics, Severity},
+    lexer::{
+        self,
+        tokens::{Span, Token, TokenType},
+    },
 };
+
+pub use crate::lexer::tokens::Precedence;
 use std::iter::Peekable;
 
 use self::{
     ast::{Expression, Identifier, Program, Statement},
-    error::ParseError,
+    error::{ErrorCode, ParseError},
 };
 
-struct Parser<'a> {
+/// Lexes and parses `src`, returning the program only if it parsed without errors
+pub fn parse(src: &str) -> Result<Program, Vec<ParseError>> {
+    let program = Parser::new(lexer::Lexer::new(src)).parse();
+    if program.errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(program.errors)
+    }
+}
+
+/// How many errors `parse` reports before giving up on the rest of the input
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+/// How deeply expressions may nest (through parentheses, prefix operators and so on)
+/// before the parser gives up, rather than overflowing the stack on hostile input
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+pub struct Parser<'a> {
     lexer: Peekable<lexer::Lexer<'a>>,
+    file_name: Option<String>,
+    max_errors: usize,
+    /// the span of the last token taken from the lexer, for errors about what's missing
+    /// after it
+    last_span: Option<Span>,
+    /// whether the last token taken was a `;`, which ends the statement it's in
+    after_semicolon: bool,
+    /// whether a statement has been started, so that a `let` now starts the next one
+    in_statement: bool,
+    /// the text of the doc comments read since the current statement began
+    docs: Vec<String>,
+    /// how many expressions are being parsed inside one another
+    depth: usize,
 }
 
+// errors are rare and carry their whole context, so they aren't worth boxing
+#[allow(clippy::result_large_err)]
 impl<'a> Parser<'a> {
-    fn new(l: lexer::Lexer<'a>) -> Self {
+    pub fn new(l: lexer::Lexer<'a>) -> Self {
         Parser {
             lexer: l.peekable(),
+            file_name: None,
+            max_errors: DEFAULT_MAX_ERRORS,
+            last_span: None,
+            after_semicolon: false,
+            in_statement: false,
+            docs: vec![],
+            depth: 0,
         }
     }
 
-    fn parse(&mut self) -> Program {
-        let mut program = Program::default();
+    /// Names the source being parsed (e.g. `utils.mk` or `<repl>`) so the program and its
+    /// errors can report where they came from
+    pub fn with_file_name(mut self, name: &str) -> Self {
+        self.file_name = Some(name.into());
+        self
+    }
 
-        while let Some(t) = self.lexer.peek() {
-            if t.typ == TokenType::EOF {
-                break;
+    /// Caps the number of errors reported, after which parsing stops with a final
+    /// "too many errors" error
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    pub fn parse(&mut self) -> Program {
+        let mut program = Program {
+            file_name: self.file_name.clone(),
+            ..Program::default()
+        };
+
+        while let Some(t) = self.peek() {
+            match t.typ {
+                TokenType::EOF => break,
+                // stray semicolons, as in `let x = 1;;`, are empty statements
+                TokenType::SemiColon => {
+                    program.warnings.push(Diagnostic {
+                        severity: Severity::Warning,
+                        code: None,
+                        message: "unnecessary ';'".into(),
+                        span: Some(t.span()),
+                        file_name: self.file_name.clone(),
+                        help: Some("an empty statement does nothing, so it can be removed".into()),
+                    });
+                    self.lexer.next();
+                    self.docs.clear();
+                    continue;
+                }
+                _ => {}
             }
-            match self.parse_statement() {
+            let start = t.loc;
+            let result = self.parse_statement();
+            // doc comments only document the statement they precede
+            self.docs.clear();
+            self.in_statement = false;
+
+            match result {
                 Ok(s) => program.statements.push(s),
-                Err(e) => program.errors.push(e),
+                Err(mut e) => {
+                    self.recover();
+                    e.file_name = self.file_name.clone();
+                    e.statement_span = self.last_span.map(|end| Span {
+                        start,
+                        end: end.end,
+                    });
+                    program.errors.push(e);
+
+                    if program.errors.len() >= self.max_errors {
+                        let mut e = ParseError::new(
+                            ErrorCode::TooManyErrors,
+                            format!("too many errors ({}), aborting", program.errors.len()),
+                            None,
+                        );
+                        e.file_name = self.file_name.clone();
+                        program.errors.push(e);
+                        break;
+                    }
+                }
             }
         }
 
         program
     }
 
+    /// Like `parse`, but only returns the program if it has no errors, so a half-parsed
+    /// program can't be run by mistake
+    pub fn parse_checked(&mut self) -> Result<Program, Diagnostics> {
+        let program = self.parse();
+        if program.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(program.diagnostics())
+        }
+    }
+
+    /// Skips the rest of a statement that failed to parse: up to and including its `;`, or
+    /// up to the `let` that starts the next statement
+    fn recover(&mut self) {
+        if !self.after_semicolon {
+            while let Some(t) = self.peek() {
+                match t.typ {
+                    TokenType::Let | TokenType::EOF => break,
+                    TokenType::SemiColon => {
+                        let _ = self.next();
+                        break;
+                    }
+                    _ => {
+                        let _ = self.next();
+                    }
+                }
+            }
+        }
+    }
+
     fn parse_statement(&mut self) -> Result<Statement, ParseError> {
         let tok = self.next()?;
+        self.in_statement = true;
 
         match tok.typ {
             TokenType::Let => self.parse_let_statement(tok),
-            _ => Err(ParseError {
-                message: format!("unexpected token: {:?}", tok.typ),
-                loc: Some(tok.loc),
-            }),
+            _ => Err(ParseError::new(
+                ErrorCode::UnexpectedToken,
+                format!("unexpected token: {:?}", tok.typ),
+                Some(tok.span()),
+            )
+            .with_help("statements start with 'let'")),
         }
     }
 
     fn parse_let_statement(&mut self, start: Token) -> Result<Statement, ParseError> {
-        let id = self.expect_next(TokenType::Identifier)?;
+        let doc = (!self.docs.is_empty()).then(|| self.docs.join("\n"));
 
-        self.expect_next(TokenType::Assign)?;
+        let id = self.next()?;
+        if id.typ != TokenType::Identifier {
+            return Err(unexpected(
+                &id,
+                format!(
+                    "expected an identifier after 'let', found {}",
+                    describe(&id)
+                ),
+            ));
+        }
 
-        // TODO actually parse an expression
-        while self.next()?.typ != TokenType::SemiColon {}
+        let type_annotation = match self.peek() {
+            Some(t) if t.typ == TokenType::Colon => {
+                self.next()?;
+                let ty = self.next()?;
+                if ty.typ != TokenType::Identifier {
+                    return Err(unexpected(
+                        &ty,
+                        format!("expected a type name after ':', found {}", describe(&ty)),
+                    ));
+                }
+                Some(Identifier {
+                    value: ty.literal.clone(),
+                    token: ty,
+                })
+            }
+            _ => None,
+        };
+
+        let assign = self.next()?;
+        if assign.typ != TokenType::Assign {
+            return Err(unexpected(
+                &assign,
+                format!(
+                    "expected '=' after identifier '{}', found {}",
+                    id.literal,
+                    describe(&assign)
+                ),
+            ));
+        }
+
+        if let Some(t) = self.peek() {
+            if matches!(
+                t.typ,
+                TokenType::SemiColon | TokenType::EOF | TokenType::Let
+            ) {
+                return Err(unexpected(
+                    t,
+                    format!("missing value in let statement for '{}'", id.literal),
+                ));
+            }
+        }
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        self.expect_semicolon("let statement")?;
 
         Ok(Statement::LetStatement {
             token: start,
@@ -65,54 +257,282 @@ impl<'a> Parser<'a> {
                 value: id.literal.clone(),
                 token: id,
             },
-            value: Expression::Dummy,
+            type_annotation,
+            value,
+            doc,
         })
     }
 
-    fn expect_peek(&'a mut self, typ: TokenType) -> Result<&'a Token, ParseError> {
-        let tok = self.peek()?;
-        if tok.typ != typ {
-            Err(ParseError {
-                message: format!("expected a '{:?}' token but got '{:?}'", typ, tok.typ),
-                loc: Some(tok.loc),
-            })
-        } else {
-            Ok(tok)
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, ParseError> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            let span = self.peek().map(Token::span);
+            return Err(ParseError::new(
+                ErrorCode::NestingTooDeep,
+                format!(
+                    "expression is nested too deeply (more than {} levels)",
+                    MAX_NESTING_DEPTH
+                ),
+                span,
+            )
+            .with_help("split the expression up using intermediate 'let' statements"));
         }
+
+        self.depth += 1;
+        let result = self.parse_operators(precedence);
+        self.depth -= 1;
+        result
     }
 
-    fn peek(&'a mut self) -> Result<&'a Token, ParseError> {
-        self.lexer.peek().ok_or(ParseError {
-            message: "Unexpected end of input".into(),
-            loc: None,
+    /// Parses an operand followed by any operators binding tighter than `precedence`
+    fn parse_operators(&mut self, precedence: Precedence) -> Result<Expression, ParseError> {
+        let tok = self.next()?;
+        let mut left = self.parse_prefix(tok)?;
+        // whether `left` is an unparenthesised comparison, which another can't follow
+        let mut after_comparison = false;
+
+        while let Some(t) = self.peek() {
+            if t.typ.precedence() <= precedence {
+                break;
+            }
+
+            let tok = self.next()?;
+            let is_comparison = tok.typ.precedence() == Precedence::LessGreater;
+            if is_comparison && after_comparison {
+                return Err(ParseError::new(
+                    ErrorCode::ChainedComparison,
+                    format!("comparison operators cannot be chained, found '{}'", tok.literal),
+                    Some(tok.span()),
+                )
+                .with_help("compare each pair separately, or add parentheses if the first comparison's result is meant to be compared"));
+            }
+            after_comparison = is_comparison;
+
+            left = match tok.typ {
+                TokenType::Increment | TokenType::Decrement => self.parse_postfix(left, tok)?,
+                _ => self.parse_infix(left, tok)?,
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn parse_prefix(&mut self, tok: Token) -> Result<Expression, ParseError> {
+        match tok.typ {
+            TokenType::Identifier => Ok(Expression::Identifier(Identifier {
+                value: tok.literal.clone(),
+                token: tok,
+            })),
+            TokenType::Int => match tok.literal.parse() {
+                Ok(value) => Ok(Expression::IntegerLiteral { token: tok, value }),
+                Err(_) => Err(ParseError::new(
+                    ErrorCode::InvalidLiteral,
+                    format!("integer literal '{}' is too large", tok.literal),
+                    Some(tok.span()),
+                )),
+            },
+            TokenType::Float => match tok.literal.parse() {
+                Ok(value) => Ok(Expression::FloatLiteral { token: tok, value }),
+                Err(_) => Err(ParseError::new(
+                    ErrorCode::InvalidLiteral,
+                    format!("invalid float literal '{}'", tok.literal),
+                    Some(tok.span()),
+                )),
+            },
+            TokenType::Char => match lexer::char_value(&tok.literal) {
+                Ok(value) => Ok(Expression::CharLiteral { token: tok, value }),
+                Err(reason) => Err(ParseError::new(
+                    ErrorCode::InvalidLiteral,
+                    format!("invalid character literal {}: {}", tok.literal, reason),
+                    Some(tok.span()),
+                )),
+            },
+            TokenType::True | TokenType::False => Ok(Expression::BooleanLiteral {
+                value: tok.typ == TokenType::True,
+                token: tok,
+            }),
+            // the magnitude of i64::MIN is too large for an i64 by itself, so it's only
+            // accepted directly after a minus, as one negative literal
+            TokenType::Subtract
+                if self.peek().is_some_and(|t| {
+                    t.typ == TokenType::Int && t.literal.parse() == Ok(i64::MIN.unsigned_abs())
+                }) =>
+            {
+                let int = self.next()?;
+                Ok(Expression::IntegerLiteral {
+                    token: Token {
+                        loc: tok.loc,
+                        ..Token::new(TokenType::Int, &format!("-{}", int.literal), 0, 0)
+                    },
+                    value: i64::MIN,
+                })
+            }
+            TokenType::Not | TokenType::Subtract | TokenType::Plus => {
+                let right = self.parse_expression(Precedence::Prefix)?;
+                Ok(Expression::Prefix {
+                    token: tok,
+                    right: Box::new(right),
+                })
+            }
+            TokenType::LParen => {
+                let expression = self.parse_expression(Precedence::Lowest)?;
+                self.expect_next(TokenType::RParen)?;
+                Ok(expression)
+            }
+            TokenType::EOF => Err(ParseError::new(
+                ErrorCode::UnexpectedEof,
+                "expected an expression but reached the end of input".into(),
+                Some(tok.span()),
+            )),
+            _ => Err(ParseError::new(
+                ErrorCode::UnexpectedToken,
+                format!("expected an expression but got '{:?}'", tok.typ),
+                Some(tok.span()),
+            )),
+        }
+    }
+
+    fn parse_infix(&mut self, left: Expression, tok: Token) -> Result<Expression, ParseError> {
+        let right = self.parse_expression(tok.typ.precedence())?;
+        Ok(Expression::Infix {
+            token: tok,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    }
+
+    fn parse_postfix(&mut self, left: Expression, tok: Token) -> Result<Expression, ParseError> {
+        if !matches!(left, Expression::Identifier(_)) {
+            return Err(ParseError::new(
+                ErrorCode::UnexpectedToken,
+                format!("'{}' can only be applied to a variable", tok.literal),
+                Some(tok.span()),
+            ));
+        }
+
+        Ok(Expression::Postfix {
+            token: tok,
+            left: Box::new(left),
         })
     }
 
     fn expect_next(&mut self, typ: TokenType) -> Result<Token, ParseError> {
         let tok = self.next()?;
         if tok.typ != typ {
-            Err(ParseError {
-                message: format!("expected a '{:?}' token but got '{:?}'", typ, tok.typ),
-                loc: Some(tok.loc),
-            })
+            Err(ParseError::new(
+                ErrorCode::ExpectedToken,
+                format!("expected a '{:?}' token but got '{:?}'", typ, tok.typ),
+                Some(tok.span()),
+            ))
         } else {
             Ok(tok)
         }
     }
 
+    /// Consumes the `;` ending a statement, which may be left off the last statement of the
+    /// input. A missing `;` is reported just after the statement, and whatever follows is
+    /// left in place so it doesn't swallow the start of the next statement.
+    fn expect_semicolon(&mut self, statement: &str) -> Result<(), ParseError> {
+        let last_span = self.last_span;
+        match self.peek() {
+            Some(t) if t.typ == TokenType::SemiColon || t.typ == TokenType::Illegal => {
+                self.next().map(|_| ())
+            }
+            Some(t) if t.typ == TokenType::EOF => Ok(()),
+            None => Ok(()),
+            Some(t) => {
+                let span = last_span.map(|s| Span {
+                    start: s.end,
+                    end: s.end,
+                });
+                Err(ParseError::new(
+                    ErrorCode::ExpectedToken,
+                    format!("missing ';' after {}, found {}", statement, describe(t)),
+                    span,
+                )
+                .with_help("statements are separated by ';'"))
+            }
+        }
+    }
+
+    /// Looks at the next token, collecting any doc comments before it
+    fn peek(&mut self) -> Option<&Token> {
+        while let Some(t) = self.lexer.next_if(|t| t.typ == TokenType::DocComment) {
+            let text = t.literal.trim_start_matches('/');
+            self.docs
+                .push(text.strip_prefix(' ').unwrap_or(text).to_string());
+        }
+
+        self.lexer.peek()
+    }
+
     fn next(&mut self) -> Result<Token, ParseError> {
-        self.lexer.next().ok_or(ParseError {
-            message: "Unexpected end of input".into(),
-            loc: None,
-        })
+        // a `let` can only start a statement, so one part way through a statement is left for
+        // the next statement rather than swallowed by this one's error
+        let in_statement = self.in_statement;
+        if let Some(t) = self
+            .peek()
+            .filter(|t| in_statement && t.typ == TokenType::Let)
+        {
+            return Err(ParseError::new(
+                ErrorCode::UnexpectedToken,
+                "unexpected 'let' before the end of the statement".into(),
+                Some(t.span()),
+            )
+            .with_help("statements are separated by ';'"));
+        }
+
+        let tok = self.lexer.next().ok_or(ParseError::new(
+            ErrorCode::UnexpectedEof,
+            "Unexpected end of input".into(),
+            None,
+        ))?;
+        self.last_span = Some(tok.span());
+        self.after_semicolon = tok.typ == TokenType::SemiColon;
+
+        if tok.typ == TokenType::Illegal {
+            return Err(ParseError::new(
+                ErrorCode::IllegalToken,
+                format!(
+                    "illegal token '{}': {}",
+                    tok.literal,
+                    tok.reason.unwrap_or("unrecognised input")
+                ),
+                Some(tok.span()),
+            ));
+        }
+
+        Ok(tok)
+    }
+}
+
+/// An error for finding `tok` where something else was expected
+fn unexpected(tok: &Token, message: String) -> ParseError {
+    let code = if tok.typ == TokenType::EOF {
+        ErrorCode::UnexpectedEof
+    } else {
+        ErrorCode::ExpectedToken
+    };
+    ParseError::new(code, message, Some(tok.span()))
+}
+
+/// Names a token for error messages, e.g. `'5'` or `the end of input`
+fn describe(tok: &Token) -> String {
+    match tok.typ {
+        TokenType::EOF => "the end of input".into(),
+        // already quoted, and escaped so the message stays on one line
+        TokenType::Char => tok.literal.clone(),
+        _ => format!("'{}'", tok.literal),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{lexer, parser::ast::Identifier};
+    use crate::{
+        lexer::{self, tokens::TokenLoc},
+        parser::ast::ast,
+    };
 
-    use super::{ast::Statement, Parser};
+    use super::{error::ErrorCode, Parser};
 
     #[test]
     fn test_parse() {
@@ -126,23 +546,318 @@ mod tests {
 
         let program = parser.parse();
 
-        assert_eq!(program.statements.len(), 3);
         assert_eq!(program.errors.len(), 0);
+        assert_eq!(
+            program.statements,
+            ast![(let x 5), (let y 10), (let foobar 838383)]
+        );
+    }
+
+    #[test]
+    fn test_parse_expressions() {
+        let input = "
+        let a = -b + c * 2;
+        let b = !(c == 1.5) != false;
+        let c = a < b == b >= c;
+        let c = (a < b) > c;
+        let d = (1 + 2) / -x++;
+        let e = y-- - 1;
+        let f = +x + 1;
+        let g = c != 'a' == ('\\n' < '\\'');";
+
+        let program = super::parse(input).expect("should parse");
+        assert_eq!(
+            program.statements,
+            ast![
+                (let a (+ (-b) (* c 2))),
+                (let b (!= (!(== c 1.5)) false)),
+                (let c (== (< a b) (>= b c))),
+                (let c (> (< a b) c)),
+                (let d (/ (+ 1 2) (-(x++)))),
+                (let e (- (y--) 1)),
+                (let f (+ (+x) 1)),
+                (let g (== (!= c 'a') (< '\n' '\'')))
+            ]
+        );
+    }
 
-        let expected_ids = vec!["x", "y", "foobar"];
-        for (s, exp) in program.statements.into_iter().zip(expected_ids.iter()) {
-            assert_let_statement(s, exp)
+    #[test]
+    fn test_expression_errors() {
+        let cases = [
+            ("let x = (;", "expected an expression but got 'SemiColon'"),
+            (
+                "let x = (1 + 2;",
+                "expected a 'RParen' token but got 'SemiColon'",
+            ),
+            ("let x = 1++;", "'++' can only be applied to a variable"),
+            (
+                "let x = 5 < 10 > -5;",
+                "comparison operators cannot be chained, found '>'",
+            ),
+            (
+                "let x = 99999999999999999999;",
+                "integer literal '99999999999999999999' is too large",
+            ),
+            (
+                "let x = 9223372036854775808;",
+                "integer literal '9223372036854775808' is too large",
+            ),
+        ];
+
+        for (input, message) in cases {
+            let errors = super::parse(input).err().expect("should fail");
+            assert_eq!(errors[0].message, message, "input {:?}", input);
         }
     }
 
-    // TODO also assert expression value
-    fn assert_let_statement(s: Statement, id: &str) {
-        match s {
-            Statement::LetStatement {
-                identifier: Identifier { value, .. },
-                ..
-            } => assert_eq!(id, &value),
-            _ => panic!("not a let statement"),
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize, open: &str, close: &str| {
+            format!("let x = {}1{};", open.repeat(depth), close.repeat(depth))
+        };
+
+        super::parse(&nested(100, "(", ")")).expect("should parse");
+        super::parse(&nested(100, "- ", "")).expect("should parse");
+
+        for input in [nested(10000, "(", ")"), nested(10000, "!", "")] {
+            let errors = super::parse(&input).err().expect("should fail");
+            assert_eq!(errors[0].code, ErrorCode::NestingTooDeep);
+            assert_eq!(
+                errors[0].message,
+                "expression is nested too deeply (more than 128 levels)"
+            );
         }
     }
+
+    #[test]
+    fn test_let_errors() {
+        let cases = [
+            (
+                "let",
+                "expected an identifier after 'let', found the end of input",
+                3,
+            ),
+            (
+                "let 5 = x;",
+                "expected an identifier after 'let', found '5'",
+                4,
+            ),
+            (
+                "let x 5;",
+                "expected '=' after identifier 'x', found '5'",
+                6,
+            ),
+            (
+                "let x",
+                "expected '=' after identifier 'x', found the end of input",
+                5,
+            ),
+            ("let x = ;", "missing value in let statement for 'x'", 8),
+            ("let x =", "missing value in let statement for 'x'", 7),
+            // char literals are measured and shown by their source text
+            (
+                "let x = 'a' 5;",
+                "missing ';' after let statement, found '5'",
+                11,
+            ),
+            (
+                "let x = 1 '\\n';",
+                "missing ';' after let statement, found '\\n'",
+                9,
+            ),
+        ];
+
+        for (input, message, col) in cases {
+            let errors = super::parse(input).err().expect("should fail");
+            assert_eq!(errors[0].message, message, "input {:?}", input);
+            assert_eq!(errors[0].span.unwrap().start.col, col, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_semicolons() {
+        // the last statement's semicolon is optional
+        let program = super::parse("let x = 1; let y = 2").expect("should parse");
+        assert_eq!(program.statements, ast![(let x 1), (let y 2)]);
+
+        // stray semicolons are empty statements, which are warned about
+        let program = super::parse(";; let x = 1;;\n;let y = 2;").expect("should parse");
+        assert_eq!(program.statements, ast![(let x 1), (let y 2)]);
+        let locations: Vec<(usize, usize)> = program
+            .warnings
+            .iter()
+            .map(|w| w.span.unwrap().start)
+            .map(|loc| (loc.line, loc.col))
+            .collect();
+        assert_eq!(locations, [(1, 0), (1, 1), (1, 13), (2, 0)]);
+        assert_eq!(
+            program.warnings[0].to_string(),
+            "1:1: warning: unnecessary ';'
+  = help: an empty statement does nothing, so it can be removed"
+        );
+
+        // a missing semicolon is reported after the statement, and doesn't swallow the next
+        let program = Parser::new(lexer::Lexer::new("let x = 1 + a\nlet y = 2;")).parse();
+        assert_eq!(program.errors.len(), 1);
+        assert_eq!(
+            program.errors[0].to_string(),
+            "At line=1, col=14: missing ';' after let statement, found 'let'"
+        );
+        assert_eq!(program.statements, ast![(let y 2)]);
+    }
+
+    #[test]
+    fn test_statement_spans() {
+        let program = Parser::new(lexer::Lexer::new("let a = 1;\n  let b = (1 + ;")).parse();
+        let span = program.errors[0].statement_span.unwrap();
+        assert_eq!(
+            span.start,
+            TokenLoc {
+                line: 2,
+                col: 2,
+                offset: 13
+            }
+        );
+        assert_eq!(
+            span.end,
+            TokenLoc {
+                line: 2,
+                col: 16,
+                offset: 27
+            }
+        );
+
+        // a missing ';' is found after the statement ends
+        let program = Parser::new(lexer::Lexer::new("let a = 1 + 2 let")).parse();
+        let span = program.errors[0].statement_span.unwrap();
+        assert_eq!(
+            span.start,
+            TokenLoc {
+                line: 1,
+                col: 0,
+                offset: 0
+            }
+        );
+        assert_eq!(
+            span.end,
+            TokenLoc {
+                line: 1,
+                col: 13,
+                offset: 13
+            }
+        );
+
+        // the rest of a broken statement is skipped, up to its ';'
+        let program = Parser::new(lexer::Lexer::new("let 5 = x; let y = 1;")).parse();
+        assert_eq!(program.errors.len(), 1);
+        let span = program.errors[0].statement_span.unwrap();
+        assert_eq!(
+            span.start,
+            TokenLoc {
+                line: 1,
+                col: 0,
+                offset: 0
+            }
+        );
+        assert_eq!(
+            span.end,
+            TokenLoc {
+                line: 1,
+                col: 10,
+                offset: 10
+            }
+        );
+        assert_eq!(program.statements, ast![(let y 1)]);
+
+        // ... or up to the 'let' starting the next statement
+        let program = Parser::new(lexer::Lexer::new("let x = (1\nlet y = 1;")).parse();
+        assert_eq!(program.errors.len(), 1);
+        assert_eq!(
+            program.errors[0].to_string(),
+            "At line=2, col=1: unexpected 'let' before the end of the statement"
+        );
+        let span = program.errors[0].statement_span.unwrap();
+        assert_eq!(
+            span.end,
+            TokenLoc {
+                line: 1,
+                col: 10,
+                offset: 10
+            }
+        );
+        assert_eq!(program.statements, ast![(let y 1)]);
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        let program = super::parse("let x: int = 5; let y = 1;").expect("should parse");
+        assert_eq!(program.statements, ast![(let x (type int) 5), (let y 1)]);
+
+        let errors = super::parse("let x: = 5;").err().expect("should fail");
+        assert_eq!(
+            errors[0].message,
+            "expected a type name after ':', found '='"
+        );
+
+        let errors = super::parse("let x:").err().expect("should fail");
+        assert_eq!(
+            errors[0].message,
+            "expected a type name after ':', found the end of input"
+        );
+    }
+
+    #[test]
+    fn test_file_name_in_errors() {
+        let lex = lexer::Lexer::new("let x = 1;\n  let = 2;");
+        let program = Parser::new(lex).with_file_name("utils.mk").parse();
+
+        assert_eq!(program.file_name.as_deref(), Some("utils.mk"));
+        assert_eq!(
+            program.errors[0].to_string(),
+            "utils.mk:2:7: expected an identifier after 'let', found '='"
+        );
+    }
+
+    #[test]
+    fn test_max_errors() {
+        let input = "1; 2; 3; 4; 5; 6;";
+
+        let program = Parser::new(lexer::Lexer::new(input))
+            .with_max_errors(3)
+            .parse();
+
+        assert_eq!(program.errors.len(), 4);
+        assert_eq!(program.errors[3].message, "too many errors (3), aborting");
+
+        let program = Parser::new(lexer::Lexer::new(input)).parse();
+        assert_eq!(program.errors.len(), 6);
+    }
+
+    #[test]
+    fn test_parse_fn() {
+        let program = super::parse("let x = 1;").expect("should parse");
+        assert_eq!(program.statements, ast![(let x 1)]);
+
+        let errors = super::parse("let = 1;").err().expect("should fail");
+        assert_eq!(
+            errors[0].message,
+            "expected an identifier after 'let', found '='"
+        );
+    }
+
+    #[test]
+    fn test_parse_checked() {
+        let program = Parser::new(lexer::Lexer::new("let x = 1;")).parse_checked();
+        assert_eq!(program.unwrap().statements, ast![(let x 1)]);
+
+        let diagnostics = Parser::new(lexer::Lexer::new("let = 1;"))
+            .with_file_name("main.mk")
+            .parse_checked()
+            .err()
+            .expect("should fail");
+        assert_eq!(
+            diagnostics.items[0].to_string(),
+            "main.mk:1:5: error[E0002]: expected an identifier after 'let', found '='"
+        );
+    }
 }
@@ -0,0 +1,157 @@
+//! Which binding each identifier refers to, worked out once per program and shared by the
+//! tools that need it (linting, go-to-definition, resolution).
+
+use super::{
+    ast::{Identifier, Program, Statement},
+    visit::{walk_expression, Visitor},
+};
+use crate::lexer::tokens::{Span, TokenLoc};
+
+use std::collections::HashMap;
+
+/// One binding: where it is defined and everywhere it is used. Redefining a name with
+/// another `let` creates a new symbol that shadows the old one.
+#[derive(Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub definition: Span,
+    pub references: Vec<Span>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Analysis {
+    /// in order of definition
+    pub symbols: Vec<Symbol>,
+    /// uses of names that aren't bound at that point
+    pub unresolved: Vec<(String, Span)>,
+}
+
+impl Analysis {
+    pub fn new(program: &Program) -> Self {
+        let mut resolver = Resolver::default();
+        resolver.visit_program(program);
+        resolver.analysis
+    }
+
+    /// The symbol whose definition or one of whose references covers `loc`
+    pub fn symbol_at(&self, loc: TokenLoc) -> Option<&Symbol> {
+        let covers = |span: &Span| {
+            span.start.line == loc.line && span.start.col <= loc.col && loc.col < span.end.col
+        };
+
+        self.symbols
+            .iter()
+            .find(|s| covers(&s.definition) || s.references.iter().any(covers))
+    }
+}
+
+impl Program {
+    /// The program's symbols, computed on first use. Call `invalidate_analysis` after
+    /// changing `statements` directly so it is recomputed.
+    pub fn analysis(&self) -> &Analysis {
+        self.analysis.get_or_init(|| Analysis::new(self))
+    }
+
+    pub fn invalidate_analysis(&mut self) {
+        self.analysis.take();
+    }
+}
+
+#[derive(Default)]
+struct Resolver {
+    analysis: Analysis,
+    /// the index of the symbol each name currently refers to
+    scope: HashMap<String, usize>,
+}
+
+impl Visitor for Resolver {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::LetStatement {
+                identifier, value, ..
+            } => {
+                // the value can't see the binding it's defining
+                walk_expression(self, value);
+
+                self.scope
+                    .insert(identifier.value.clone(), self.analysis.symbols.len());
+                self.analysis.symbols.push(Symbol {
+                    name: identifier.value.clone(),
+                    definition: identifier.token.span(),
+                    references: vec![],
+                });
+            }
+        }
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        let span = identifier.token.span();
+        match self.scope.get(&identifier.value) {
+            Some(&i) => self.analysis.symbols[i].references.push(span),
+            None => self
+                .analysis
+                .unresolved
+                .push((identifier.value.clone(), span)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::{tokens::TokenLoc, Lexer},
+        parser::{transform::PassManager, Parser},
+    };
+
+    #[test]
+    fn test_analysis() {
+        let src = "let a = 1;\nlet b = a + c;\nlet a = a * b;";
+        let program = Parser::new(Lexer::new(src)).parse();
+        let analysis = program.analysis();
+
+        let summary: Vec<(&str, usize, usize)> = analysis
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.definition.start.line, s.references.len()))
+            .collect();
+        assert_eq!(summary, vec![("a", 1, 2), ("b", 2, 1), ("a", 3, 0)]);
+
+        assert_eq!(analysis.unresolved.len(), 1);
+        assert_eq!(analysis.unresolved[0].0, "c");
+
+        // the `a` being multiplied is the first binding, not the one being defined
+        let symbol = analysis
+            .symbol_at(TokenLoc {
+                line: 3,
+                col: 8,
+                offset: 34,
+            })
+            .unwrap();
+        assert_eq!(
+            symbol.definition.start,
+            TokenLoc {
+                line: 1,
+                col: 4,
+                offset: 4
+            }
+        );
+        assert!(analysis
+            .symbol_at(TokenLoc {
+                line: 3,
+                col: 0,
+                offset: 26
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn test_analysis_is_cached() {
+        let mut program = Parser::new(Lexer::new("let a = 1;")).parse();
+        let first: *const _ = program.analysis();
+        assert_eq!(first, program.analysis() as *const _);
+
+        program.statements.clear();
+        PassManager::default().run(&mut program);
+        assert!(program.analysis().symbols.is_empty());
+    }
+}
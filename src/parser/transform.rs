@@ -0,0 +1,179 @@
+use super::{
+    ast::{Expression, Program},
+    visit::{walk_expression_mut, walk_program_mut, VisitorMut},
+};
+use crate::lexer::tokens::{Token, TokenType};
+
+/// A single AST->AST rewrite, such as desugaring a construct into simpler ones
+pub trait Pass {
+    fn name(&self) -> &'static str;
+
+    fn run(&mut self, program: &mut Program);
+}
+
+/// Applies a sequence of passes to a program, in the order they were added
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    pub fn add_pass(&mut self, pass: impl Pass + 'static) -> &mut Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|p| p.name()).collect()
+    }
+
+    pub fn run(&mut self, program: &mut Program) {
+        for pass in self.passes.iter_mut() {
+            pass.run(program)
+        }
+        program.invalidate_analysis();
+    }
+}
+
+/// Collapses a sign applied to a numeric literal (`-5`, `+1.5`) into the literal itself, so
+/// later stages see a negative number rather than a negation
+pub struct FoldSignedLiterals;
+
+impl Pass for FoldSignedLiterals {
+    fn name(&self) -> &'static str {
+        "fold-signed-literals"
+    }
+
+    fn run(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+}
+
+impl VisitorMut for FoldSignedLiterals {
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+
+        let Expression::Prefix { token: op, right } = expression else {
+            return;
+        };
+        let negate = match op.typ {
+            TokenType::Subtract => true,
+            TokenType::Plus => false,
+            _ => return,
+        };
+
+        // the folded literal takes the operator's location and its sign
+        let signed = |literal: &Token| {
+            let text = match literal.literal.strip_prefix('-') {
+                Some(positive) if negate => positive.to_string(),
+                None if negate => format!("-{}", literal.literal),
+                _ => literal.literal.clone(),
+            };
+            Token {
+                loc: op.loc,
+                ..Token::new(literal.typ, &text, 0, 0)
+            }
+        };
+
+        let folded = match right.as_ref() {
+            Expression::IntegerLiteral { token, value } => {
+                // -i64::MIN overflows, so that negation is left for later stages
+                let value = if negate {
+                    value.checked_neg()
+                } else {
+                    Some(*value)
+                };
+                let Some(value) = value else {
+                    return;
+                };
+                Expression::IntegerLiteral {
+                    token: signed(token),
+                    value,
+                }
+            }
+            Expression::FloatLiteral { token, value } => Expression::FloatLiteral {
+                token: signed(token),
+                value: if negate { -value } else { *value },
+            },
+            _ => return,
+        };
+        *expression = folded;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FoldSignedLiterals, Pass, PassManager};
+    use crate::{
+        lexer,
+        parser::{
+            ast::{Expression, Identifier, Program, Statement},
+            visit::{walk_program_mut, VisitorMut},
+            Parser,
+        },
+    };
+
+    struct Suffix(&'static str);
+
+    impl VisitorMut for Suffix {
+        fn visit_identifier_mut(&mut self, identifier: &mut Identifier) {
+            identifier.value.push_str(self.0)
+        }
+    }
+
+    impl Pass for Suffix {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn run(&mut self, program: &mut Program) {
+            walk_program_mut(self, program)
+        }
+    }
+
+    #[test]
+    fn test_passes_run_in_order() {
+        let mut program = Parser::new(lexer::Lexer::new("let x = 1;")).parse();
+
+        let mut manager = PassManager::default();
+        manager.add_pass(Suffix("_a")).add_pass(Suffix("_b"));
+        assert_eq!(manager.pass_names(), vec!["_a", "_b"]);
+
+        manager.run(&mut program);
+
+        match &program.statements[0] {
+            Statement::LetStatement { identifier, .. } => assert_eq!(identifier.value, "x_a_b"),
+        }
+    }
+
+    #[test]
+    fn test_fold_signed_literals() {
+        let mut program = Parser::new(lexer::Lexer::new("let x = -5 * +2.5 - -y + -(-1);")).parse();
+        FoldSignedLiterals.run(&mut program);
+
+        assert_eq!(
+            program.to_sexpr(),
+            "(program (let x (+ (- (* -5 2.5) (- y)) 1)))"
+        );
+
+        let mut program = Parser::new(lexer::Lexer::new(
+            "let m = -9223372036854775808; let n = -(-9223372036854775808);",
+        ))
+        .parse();
+        FoldSignedLiterals.run(&mut program);
+        assert_eq!(
+            program.to_sexpr(),
+            "(program (let m -9223372036854775808) (let n (- -9223372036854775808)))"
+        );
+
+        let mut program = Program::new(vec![Statement::let_(
+            "m",
+            Expression::prefix("-", Expression::int(i64::MIN)),
+        )]);
+        FoldSignedLiterals.run(&mut program);
+        assert_eq!(
+            program.to_sexpr(),
+            "(program (let m (- -9223372036854775808)))"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use super::ast::{Expression, Identifier, Program, Statement};
+
+/// Read-only traversal of the AST. Every method defaults to recursing into the node's
+/// children via the matching `walk_*` function, so implementors only override the nodes
+/// they care about and call `walk_*` themselves to keep descending.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression)
+    }
+
+    fn visit_identifier(&mut self, _identifier: &Identifier) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for statement in &program.statements {
+        visitor.visit_statement(statement)
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::LetStatement {
+            identifier, value, ..
+        } => {
+            visitor.visit_identifier(identifier);
+            visitor.visit_expression(value)
+        }
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Identifier(identifier) => visitor.visit_identifier(identifier),
+        Expression::Prefix { right, .. } => visitor.visit_expression(right),
+        Expression::Postfix { left, .. } => visitor.visit_expression(left),
+        Expression::Infix { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right)
+        }
+        Expression::Dummy
+        | Expression::IntegerLiteral { .. }
+        | Expression::FloatLiteral { .. }
+        | Expression::BooleanLiteral { .. }
+        | Expression::CharLiteral { .. } => {}
+    }
+}
+
+/// Like `Visitor`, but with mutable access so passes can rewrite nodes in place
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement)
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression)
+    }
+
+    fn visit_identifier_mut(&mut self, _identifier: &mut Identifier) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for statement in &mut program.statements {
+        visitor.visit_statement_mut(statement)
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::LetStatement {
+            identifier, value, ..
+        } => {
+            visitor.visit_identifier_mut(identifier);
+            visitor.visit_expression_mut(value)
+        }
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::Identifier(identifier) => visitor.visit_identifier_mut(identifier),
+        Expression::Prefix { right, .. } => visitor.visit_expression_mut(right),
+        Expression::Postfix { left, .. } => visitor.visit_expression_mut(left),
+        Expression::Infix { left, right, .. } => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right)
+        }
+        Expression::Dummy
+        | Expression::IntegerLiteral { .. }
+        | Expression::FloatLiteral { .. }
+        | Expression::BooleanLiteral { .. }
+        | Expression::CharLiteral { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Visitor, VisitorMut};
+    use crate::{
+        lexer,
+        parser::{ast::Identifier, Parser},
+    };
+
+    struct IdentifierCollector(Vec<String>);
+
+    impl Visitor for IdentifierCollector {
+        fn visit_identifier(&mut self, identifier: &Identifier) {
+            self.0.push(identifier.value.clone())
+        }
+    }
+
+    struct Renamer;
+
+    impl VisitorMut for Renamer {
+        fn visit_identifier_mut(&mut self, identifier: &mut Identifier) {
+            identifier.value = identifier.value.to_uppercase()
+        }
+    }
+
+    #[test]
+    fn test_visitors() {
+        let mut program = Parser::new(lexer::Lexer::new("let x = 1; let y = 2;")).parse();
+
+        Renamer.visit_program_mut(&mut program);
+
+        let mut collector = IdentifierCollector(vec![]);
+        collector.visit_program(&program);
+
+        assert_eq!(collector.0, vec!["X", "Y"]);
+    }
+}
@@ -1,16 +1,368 @@
-use crate::lexer;
+use crate::{
+    diagnostic, lexer,
+    parser::{error::ParseError, Parser},
+};
 
-const PROMPT: &'static str = ">> ";
+use std::{
+    io::{IsTerminal, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+pub struct ReplConfig {
+    pub prompt: String,
+    /// shown for each line of a `:paste` block
+    pub continuation_prompt: String,
+    /// whether to highlight diagnostics with ANSI colours
+    pub color: bool,
+    /// a file every input is appended to, if set
+    pub history_file: Option<PathBuf>,
+    /// inputs taking longer than this get a timing note even when `:time` is off
+    pub slow_threshold: Duration,
+}
+
+impl Default for ReplConfig {
+    fn default() -> Self {
+        ReplConfig {
+            prompt: ">> ".into(),
+            continuation_prompt: ".. ".into(),
+            color: false,
+            history_file: None,
+            slow_threshold: Duration::from_millis(500),
+        }
+    }
+}
+
+impl ReplConfig {
+    /// Reads overrides from `MONKEY_PROMPT`, `MONKEY_CONTINUATION_PROMPT`, `MONKEY_COLOR`
+    /// (`always`/`never`, otherwise colour when stdout is a terminal), `MONKEY_HISTORY`
+    /// and `MONKEY_SLOW_MS`
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut config = ReplConfig::default();
+
+        if let Some(prompt) = var("MONKEY_PROMPT") {
+            config.prompt = prompt;
+        }
+        if let Some(prompt) = var("MONKEY_CONTINUATION_PROMPT") {
+            config.continuation_prompt = prompt;
+        }
+        config.color = diagnostic::color_choice(var("MONKEY_COLOR").as_deref())
+            .unwrap_or_else(|| std::io::stdout().is_terminal());
+        config.history_file = var("MONKEY_HISTORY").map(PathBuf::from);
+        if let Some(ms) = var("MONKEY_SLOW_MS").and_then(|ms| ms.parse().ok()) {
+            config.slow_threshold = Duration::from_millis(ms);
+        }
+
+        config
+    }
+}
+
+pub fn start(
+    reader: &mut dyn std::io::BufRead,
+    writer: &mut dyn std::io::Write,
+    config: &ReplConfig,
+) {
+    let mut timing = false;
+    let mut slow_threshold = config.slow_threshold;
+    // the source of every input that parsed cleanly, for `:save`
+    let mut history: Vec<String> = vec![];
 
-pub fn start(reader: &mut dyn std::io::BufRead, writer: &mut dyn std::io::Write) {
     loop {
-        write!(writer, "{}", PROMPT).expect("failed to write");
+        write!(writer, "{}", config.prompt).expect("failed to write");
         writer.flush().expect("failed to flush");
         let mut input: String = String::new();
-        reader.read_line(&mut input).expect("failed to read");
-        let l = lexer::Lexer::new(&input);
-        for i in l {
-            write!(writer, "{:?}\n", i).expect("failed to write");
+        if reader.read_line(&mut input).expect("failed to read") == 0 {
+            break;
+        }
+
+        if let Some(path) = &config.history_file {
+            append_history(path, &input);
+        }
+
+        if let Some(arg) = input.strip_prefix(":time") {
+            match arg.trim() {
+                "" => {
+                    timing = !timing;
+                    let state = if timing { "on" } else { "off" };
+                    writeln!(writer, "timing {}", state).expect("failed to write");
+                }
+                ms => match ms.parse() {
+                    Ok(ms) => {
+                        slow_threshold = Duration::from_millis(ms);
+                        writeln!(writer, "slow input threshold set to {}ms", ms)
+                            .expect("failed to write");
+                    }
+                    Err(_) => {
+                        writeln!(writer, "usage: :time [threshold in ms]").expect("failed to write")
+                    }
+                },
+            }
+            continue;
+        }
+
+        if let Some(path) = input.strip_prefix(":save") {
+            save_session(path.trim(), &history, writer);
+            continue;
         }
+
+        if let Some(path) = input.strip_prefix(":replay") {
+            match std::fs::read_to_string(path.trim()) {
+                Ok(src) => input = src,
+                Err(e) => {
+                    writeln!(writer, "failed to read {}: {}", path.trim(), e)
+                        .expect("failed to write");
+                    continue;
+                }
+            }
+        }
+
+        if input.trim() == ":paste" {
+            writeln!(writer, "(paste mode: finish with :end on its own line)")
+                .expect("failed to write");
+            input = read_paste(reader, writer, config);
+        }
+
+        let started = Instant::now();
+        let succeeded = run_input(&input, writer, config);
+        let elapsed = started.elapsed();
+
+        if succeeded {
+            let src = input.strip_prefix(":ast").map_or(&*input, str::trim_start);
+            history.push(src.into());
+        }
+
+        if timing {
+            writeln!(writer, "time: {:?}", elapsed).expect("failed to write");
+        } else if elapsed > slow_threshold {
+            writeln!(writer, "note: input took {:?}", elapsed).expect("failed to write");
+        }
+    }
+}
+
+/// Runs a machine-readable session for editors and notebooks: each input line yields one
+/// JSON object, `{"ok":true,"ast":"..."}` or `{"ok":false,"errors":[...]}`
+pub fn start_json(reader: &mut dyn std::io::BufRead, writer: &mut dyn std::io::Write) {
+    loop {
+        let mut input = String::new();
+        if reader.read_line(&mut input).expect("failed to read") == 0 {
+            break;
+        }
+
+        let program = Parser::new(lexer::Lexer::new(&input))
+            .with_file_name("<repl>")
+            .parse();
+
+        let response = if program.errors.is_empty() {
+            format!(
+                "{{\"ok\":true,\"ast\":{}}}",
+                json_string(&program.to_sexpr())
+            )
+        } else {
+            let errors: Vec<String> = program.errors.iter().map(json_error).collect();
+            format!("{{\"ok\":false,\"errors\":[{}]}}", errors.join(","))
+        };
+
+        writeln!(writer, "{}", response).expect("failed to write");
+        writer.flush().expect("failed to flush");
+    }
+}
+
+fn json_error(e: &ParseError) -> String {
+    let location = match e.span {
+        Some(span) => format!(
+            ",\"line\":{},\"col\":{}",
+            span.start.line,
+            span.start.col + 1
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        "{{\"code\":\"{}\",\"message\":{}{}}}",
+        e.code.as_str(),
+        json_string(&e.message),
+        location
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Buffers lines up to `:end` (or end of input) so a pasted multi-line snippet is handled
+/// as one program
+fn read_paste(
+    reader: &mut dyn std::io::BufRead,
+    writer: &mut dyn std::io::Write,
+    config: &ReplConfig,
+) -> String {
+    let mut buffer = String::new();
+    loop {
+        write!(writer, "{}", config.continuation_prompt).expect("failed to write");
+        writer.flush().expect("failed to flush");
+        let mut line = String::new();
+        if reader.read_line(&mut line).expect("failed to read") == 0 || line.trim() == ":end" {
+            return buffer;
+        }
+        buffer.push_str(&line);
+    }
+}
+
+fn append_history(path: &PathBuf, input: &str) {
+    // history is a convenience, so failing to write it shouldn't interrupt the session
+    let _ = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(input.as_bytes()));
+}
+
+fn save_session(path: &str, history: &[String], writer: &mut dyn std::io::Write) {
+    let mut contents = String::new();
+    for src in history {
+        contents.push_str(src);
+        if !src.ends_with('\n') {
+            contents.push('\n');
+        }
+    }
+
+    match std::fs::write(path, contents) {
+        Ok(()) => writeln!(writer, "saved {} inputs to {}", history.len(), path),
+        Err(e) => writeln!(writer, "failed to save to {}: {}", path, e),
+    }
+    .expect("failed to write");
+}
+
+/// Handles one input, returning whether its source parsed without errors
+fn run_input(input: &str, writer: &mut dyn std::io::Write, config: &ReplConfig) -> bool {
+    if let Some(src) = input.strip_prefix(":ast") {
+        let program = Parser::new(lexer::Lexer::new(src))
+            .with_file_name("<repl>")
+            .parse();
+        let diagnostics = program.diagnostics();
+        write!(writer, "{}", diagnostics.render(config.color)).expect("failed to write");
+        writeln!(writer, "{}", program.to_sexpr()).expect("failed to write");
+        return program.errors.is_empty();
+    }
+
+    let l = lexer::Lexer::new(input);
+    for i in l {
+        writeln!(writer, "{:?}", i).expect("failed to write");
+    }
+
+    crate::parser::parse(input).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{start, start_json, ReplConfig};
+
+    fn run(input: &str) -> String {
+        let mut output = vec![];
+        start(&mut input.as_bytes(), &mut output, &ReplConfig::default());
+        String::from_utf8(output).expect("output should be utf-8")
+    }
+
+    #[test]
+    fn test_time_command() {
+        let output = run(":time\n:ast let x = 1;\n:time\n:ast let x = 1;\n:time 10\n:time x\n");
+        let lines: Vec<&str> = output.split(">> ").collect();
+
+        assert_eq!(lines[1], "timing on\n");
+        assert!(lines[2].starts_with("(program (let x 1))\ntime: "));
+        assert_eq!(lines[3], "timing off\n");
+        assert_eq!(lines[4], "(program (let x 1))\n");
+        assert_eq!(lines[5], "slow input threshold set to 10ms\n");
+        assert_eq!(lines[6], "usage: :time [threshold in ms]\n");
+    }
+
+    #[test]
+    fn test_paste() {
+        let output = run(":paste\n:ast let x = 1;\nlet y\n= 2;\n:end\n");
+        let lines: Vec<&str> = output.split(">> ").collect();
+
+        assert_eq!(
+            lines[1],
+            "(paste mode: finish with :end on its own line)\n.. .. .. .. (program (let x 1) (let y 2))\n"
+        );
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn test_save_and_replay() {
+        let path = std::env::temp_dir().join(format!("monkey-repl-{}.mk", std::process::id()));
+        let path = path.to_str().expect("temp path should be utf-8");
+
+        let output = run(&format!(
+            ":ast let x = 1;\n:ast let = 2;\n:paste\nlet y\n= 3;\n:end\n:save {}\n",
+            path
+        ));
+        assert!(output.ends_with(&format!("saved 2 inputs to {}\n>> ", path)));
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "let x = 1;\nlet y\n= 3;\n"
+        );
+
+        let output = run(&format!(":replay {}\n", path));
+        assert!(output.contains("Token { typ: Identifier, literal: \"y\""));
+
+        std::fs::remove_file(path).unwrap();
+        let output = run(&format!(":replay {}\n", path));
+        assert!(output.starts_with(&format!(">> failed to read {}: ", path)));
+    }
+
+    #[test]
+    fn test_json_protocol() {
+        let mut output = vec![];
+        start_json(&mut "let x = 1;\nlet x \"\n".as_bytes(), &mut output);
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], r#"{"ok":true,"ast":"(program (let x 1))"}"#);
+        assert_eq!(
+            lines[1],
+            r#"{"ok":false,"errors":[{"code":"E0004","message":"illegal token '\"': unexpected character","line":1,"col":7}]}"#
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_config() {
+        let config = ReplConfig::from_vars(|name| match name {
+            "MONKEY_PROMPT" => Some("monkey> ".into()),
+            "MONKEY_COLOR" => Some("always".into()),
+            "MONKEY_SLOW_MS" => Some("20".into()),
+            _ => None,
+        });
+        assert_eq!(config.prompt, "monkey> ");
+        assert_eq!(config.continuation_prompt, ".. ");
+        assert!(config.color);
+        assert_eq!(config.history_file, None);
+        assert_eq!(config.slow_threshold.as_millis(), 20);
+
+        let mut output = vec![];
+        start(&mut ":ast let;\n".as_bytes(), &mut output, &config);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "monkey> \x1b[31m<repl>:1:5: error[E0002]: expected an identifier after 'let', found ';'\x1b[0m\n1 error\n(program)\nmonkey> "
+        );
     }
 }
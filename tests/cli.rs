@@ -0,0 +1,234 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_monkey-rs"))
+        .args(args)
+        .output()
+        .expect("failed to start monkey-rs")
+}
+
+fn run_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_monkey-rs"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start monkey-rs");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_piped_program() {
+    let output = run_with_stdin(&[], "let x = 1;\nlet y = 2;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+
+    let output = run_with_stdin(&[], "let x = 1;\nlet = 2;\n");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.lines().next(),
+        Some("<stdin>:2:5: error[E0002]: expected an identifier after 'let', found '='")
+    );
+}
+
+#[test]
+fn test_deeply_nested_program() {
+    let src = format!("let x = {}1{};", "(".repeat(20000), ")".repeat(20000));
+    let output = run_with_stdin(&[], &src);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("<stdin>:1:137: error[E0008]"));
+}
+
+#[test]
+fn test_shebang_script() {
+    let output = run_with_stdin(&[], "#!/usr/bin/env monkey\nlet x = 1;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_eval_flag() {
+    let output = run(&["-e", "let x = 1;"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+
+    let output = run(&["--eval", "let = 1;"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("<eval>:1:5: error[E0002]"));
+
+    let output = run(&["-e"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_color_flag() {
+    let output = run(&["--color", "always", "-e", "let = 1;"]);
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("\x1b[31m<eval>:1:5: error"));
+
+    // stderr isn't a terminal here, so auto means no colour
+    for args in [&["--color", "never"][..], &[]] {
+        let output = run(&[args, &["-e", "let = 1;"]].concat());
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("<eval>:1:5: error"));
+    }
+
+    // the REPL's diagnostics go to stdout, which is piped here too
+    let output = run_with_stdin(&["repl"], ":ast let = 1;\n");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains('\x1b'));
+    let output = run_with_stdin(&["--color", "always", "repl"], ":ast let = 1;\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\x1b[31m<repl>:1:6: error"));
+}
+
+#[test]
+fn test_run_command() {
+    let dir = std::env::temp_dir().join(format!("monkey-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("broken.mk");
+    std::fs::write(&script, "let x = 1;\nlet = 2;\n").unwrap();
+
+    let output = run(&["run", script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with(&format!("{}:2:5: error[E0002]", script.display())));
+
+    let output = run(&["run", dir.join("missing.mk").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_usage_errors() {
+    let output = run(&["--help"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("usage: monkey"));
+
+    let output = run(&["lint"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("error: the 'lint' command is not available yet"));
+}
+
+#[test]
+fn test_fmt() {
+    let output = run_with_stdin(&["fmt"], "let  a=1+2 ;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "let a = 1 + 2;\n");
+
+    let output = run_with_stdin(&["fmt", "--check"], "let  a=1+2 ;");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "<stdin>\n");
+
+    let output = run_with_stdin(&["fmt", "--check"], "let a = 1 + 2;\n");
+    assert_eq!(output.status.code(), Some(0));
+
+    let dir = std::env::temp_dir().join(format!("monkey-fmt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("script.mk");
+    std::fs::write(&script, "let  a=1+2 ;").unwrap();
+
+    let output = run(&["fmt", script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        std::fs::read_to_string(&script).unwrap(),
+        "let a = 1 + 2;\n"
+    );
+
+    // settings come from the formatted file's project, not the working directory's
+    std::fs::create_dir_all(dir.join("project")).unwrap();
+    std::fs::write(
+        dir.join("project/monkey.toml"),
+        "[fmt]\nspaces_around_operators = false\n",
+    )
+    .unwrap();
+    let script = dir.join("project/script.mk");
+    std::fs::write(&script, "let  a=1+2 ;").unwrap();
+
+    let output = run(&["fmt", script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(std::fs::read_to_string(&script).unwrap(), "let a = 1+2;\n");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_project_entry() {
+    let dir = std::env::temp_dir().join(format!("monkey-project-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("monkey.toml"),
+        "[project]\nentry = \"src/main.mk\"\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("src/main.mk"), "let = 1;\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_monkey-rs"))
+        .arg("run")
+        .current_dir(dir.join("src"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("src/main.mk:1:5: error[E0002]"));
+
+    // a broken config has its own exit status
+    std::fs::write(dir.join("monkey.toml"), "[project]\nentry = 5\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_monkey-rs"))
+        .arg("run")
+        .current_dir(dir.join("src"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_watch() {
+    let dir = std::env::temp_dir().join(format!("monkey-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("w.mk");
+    std::fs::write(&script, "let = 1;\n").unwrap();
+
+    // stdout and stderr share a file, so their relative order is kept
+    let log = dir.join("out");
+    let out = std::fs::File::create(&log).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_monkey-rs"))
+        .args(["run", "--watch", script.to_str().unwrap()])
+        .stdout(out.try_clone().unwrap())
+        .stderr(out)
+        .spawn()
+        .unwrap();
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    let mut output = String::new();
+    while !output.contains("[watching") && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        output = std::fs::read_to_string(&log).unwrap();
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    // the diagnostics come before the watching note, and the screen is only cleared on a
+    // terminal
+    let diagnostics = output.find("error[E0002]").expect("no diagnostics");
+    assert!(
+        diagnostics < output.find("[watching").unwrap(),
+        "{:?}",
+        output
+    );
+    assert!(!output.contains("\x1b[2J"), "{:?}", output);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}